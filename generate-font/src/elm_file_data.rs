@@ -143,7 +143,7 @@ impl TryFrom<&Path> for ElmFileData {
         let tree = {
             let mut parser = Parser::new();
             parser.set_language(language).unwrap();
-            parser.parse(&elm_code, None).unwrap()
+            parser.parse(elm_code, None).unwrap()
         };
         let root_node = tree.root_node();
         let mut cursor = QueryCursor::new();
@@ -42,31 +42,31 @@ impl CodePoint {
 
 impl Ord for CodePoint {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.partial_cmp(other).unwrap_or(std::cmp::Ordering::Equal)
-    }
-}
-
-impl PartialOrd for CodePoint {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         match (self, other) {
-            (CodePoint::Single(a), CodePoint::Single(b)) => a.partial_cmp(b),
+            (CodePoint::Single(a), CodePoint::Single(b)) => a.cmp(b),
             (CodePoint::Ligature(a), CodePoint::Ligature(b)) => {
                 if a.len() < b.len() {
                     // longer ligatures should come first
                     // because we want fff to be before ff
-                    Some(std::cmp::Ordering::Greater)
+                    std::cmp::Ordering::Greater
                 } else if a.len() > b.len() {
-                    Some(std::cmp::Ordering::Less)
+                    std::cmp::Ordering::Less
                 } else {
-                    a.partial_cmp(b)
+                    a.cmp(b)
                 }
             }
-            (CodePoint::Single(_), CodePoint::Ligature(_)) => Some(std::cmp::Ordering::Less),
-            (CodePoint::Ligature(_), CodePoint::Single(_)) => Some(std::cmp::Ordering::Greater),
+            (CodePoint::Single(_), CodePoint::Ligature(_)) => std::cmp::Ordering::Less,
+            (CodePoint::Ligature(_), CodePoint::Single(_)) => std::cmp::Ordering::Greater,
         }
     }
 }
 
+impl PartialOrd for CodePoint {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl TryFrom<&Path> for GlyphImages {
     type Error = Box<dyn std::error::Error>;
 
@@ -92,8 +92,8 @@ impl TryFrom<&Path> for GlyphImages {
             let img = image::open(&path)?.to_luma8();
 
             // We fit dimensions of the image into u8
-            if img.width() > 16 || img.height() > 16 {
-                panic!("Image dimensions higher than 16: {:?}", path);
+            if img.width() > 255 || img.height() > 255 {
+                panic!("Image dimensions higher than 255: {:?}", path);
             }
 
             // Catch images that are not 11 pixels high
@@ -7,8 +7,18 @@ mod glyph_images;
 use glyph_images::{CodePoint, GlyphImages};
 
 const ATLAS_WIDTH: u32 = 128;
+// Vertical metrics of MogeeFont in pixels. The Elm source only defines the line height,
+// so these are measured on the glyph images in mogeefont/font:
+// the baseline is the first row below the lowercase letters, counting from the top of the line,
+const BASELINE: u32 = 8;
+// the underline is one row below the baseline, leaving a gap under the letters,
+const UNDERLINE_OFFSET: u32 = 1;
+// and the x-height is the height of the lowercase "x".
+const X_HEIGHT: u32 = 5;
+const BINARY_FORMAT_VERSION: u8 = 1;
 
 #[derive(ValueEnum, Clone, Default, Debug)]
+#[allow(clippy::upper_case_acronyms)]
 enum Charset {
     #[default]
     ASCII,
@@ -24,6 +34,15 @@ impl std::fmt::Display for Charset {
     }
 }
 
+/// The format of the glyph records in the glyph data
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum GlyphFormat {
+    /// 5 bytes per glyph, fits glyphs up to 15x15 pixels in a 256x256 atlas
+    Compact,
+    /// 8 bytes per glyph, with u16 atlas coordinates and u8 dimensions
+    Wide,
+}
+
 // Clapp application parameters
 #[derive(Parser)]
 struct GenerateFont {
@@ -45,18 +64,23 @@ struct GenerateFont {
     /// Default: "ascii"
     #[clap(long, value_enum, default_value = "ascii")]
     charset: Vec<Charset>,
+
+    /// Also save the charsets in the binary format,
+    /// that can be loaded at runtime
+    #[clap(long)]
+    binary: bool,
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args = GenerateFont::parse();
     let glyph_images = GlyphImages::try_from(args.font_dir.as_ref())?;
     let elm_file_data = ElmFileData::try_from(args.elm_file.as_ref())?;
-    let mut rust_file = std::fs::File::create(&Path::new(&args.out_dir).join("generated.rs"))?;
+    let mut rust_file = std::fs::File::create(Path::new(&args.out_dir).join("generated.rs"))?;
 
     write!(
         &mut rust_file,
         r#"use crate::{{
-    charset::Charset, kerning::Kerning, ligatures::Ligatures, side_bearings::SideBearings
+    charset::{{Charset, GlyphFormat}}, kerning::Kerning, ligatures::Ligatures, side_bearings::SideBearings
 }};
 use embedded_graphics::{{image::ImageRaw, mono_font::mapping::StrGlyphMapping}};
 "#
@@ -66,7 +90,7 @@ use embedded_graphics::{{image::ImageRaw, mono_font::mapping::StrGlyphMapping}};
         .charset
         .into_iter()
         .map(|charset| FontData::new(&glyph_images, &elm_file_data, charset))
-        .collect();
+        .collect::<Result<_, _>>()?;
 
     for font in fonts.iter() {
         font.write(
@@ -75,6 +99,11 @@ use embedded_graphics::{{image::ImageRaw, mono_font::mapping::StrGlyphMapping}};
             &Path::new(&args.out_dir).join(format!("{}_font.raw", font.charset)),
             &Path::new(&args.out_dir).join(format!("{}_glyph_data.raw", font.charset)),
         )?;
+        if args.binary {
+            font.save_binary_font(
+                &Path::new(&args.out_dir).join(format!("{}.mogf", font.charset)),
+            )?;
+        }
     }
 
     update_specimen(&Path::new(&args.out_dir).join("lib.rs"), &fonts)?;
@@ -105,7 +134,11 @@ struct FontData {
 }
 
 impl FontData {
-    pub fn new(glyphs_images: &GlyphImages, elm_file_data: &ElmFileData, charset: Charset) -> Self {
+    pub fn new(
+        glyphs_images: &GlyphImages,
+        elm_file_data: &ElmFileData,
+        charset: Charset,
+    ) -> Result<Self, Box<dyn Error>> {
         let GlyphImages {
             mut code_points_and_images,
         } = glyphs_images.clone();
@@ -133,10 +166,7 @@ impl FontData {
                         CodePoint::Ligature(p) => p.chars().all(|c| (c as u32) < 128),
                     });
 
-            bearings = bearings
-                .into_iter()
-                .filter(|(code_point, _)| code_point.chars().all(|c| (c as u32) < 128))
-                .collect();
+            bearings.retain(|code_point, _| code_point.chars().all(|c| (c as u32) < 128));
             left_kerning_class = left_kerning_class
                 .into_iter()
                 .map(|(c, chars)| {
@@ -274,18 +304,16 @@ impl FontData {
         // Ensure we can use binary search on the kerning overrides
         kerning_overrides.sort_by_key(|(left, right, _)| (*left, *right));
 
-        let mut left = 0;
-        let mut top = 0;
-        let mut glyphs = Vec::new();
-        // 1 pixel spacing between glyphs is for pure aesthetic reasons
-        let spacing = 1;
-        for (code_point, img) in code_points_and_images.into_iter() {
-            let width = img.width();
-            if left + width > ATLAS_WIDTH {
-                left = 0;
-                top += line_height + spacing;
-            }
-            glyphs.push(Glyph {
+        let (positions, atlas_height) = pack_glyphs(
+            code_points_and_images
+                .iter()
+                .map(|(_, img)| img.dimensions()),
+            line_height,
+        )?;
+        let glyphs = code_points_and_images
+            .into_iter()
+            .zip(positions)
+            .map(|((code_point, img), (left, top))| Glyph {
                 left,
                 top,
                 left_kerning_class: *left_kerning_classes
@@ -294,13 +322,11 @@ impl FontData {
                 right_kerning_class: *right_kerning_classes
                     .get(&code_point.as_string())
                     .unwrap_or(&0),
-                img: img.clone(),
-            });
-            left += width + spacing;
-        }
-        let atlas_height = top + line_height;
+                img,
+            })
+            .collect();
 
-        Self {
+        Ok(Self {
             charset,
             glyphs,
             glyph_code_points,
@@ -311,7 +337,7 @@ impl FontData {
             default_bearings,
             kerning_overrides,
             kering_pairs,
-        }
+        })
     }
 
     fn bitmap_data(&self) -> Result<Vec<u8>, std::num::TryFromIntError> {
@@ -348,7 +374,7 @@ impl FontData {
                 let x = x / scale;
                 let y = y / scale;
                 let index = usize::try_from(x / 8 + y * (ATLAS_WIDTH / 8)).unwrap_or_default();
-                let bit = bitmap_data[index] & (128 >> x % 8) != 0;
+                let bit = bitmap_data[index] & (128 >> (x % 8)) != 0;
                 image::Luma::from([(if bit { 255 } else { 0 })])
             });
         let width = image.width();
@@ -358,11 +384,10 @@ impl FontData {
         Ok(format!("data:image/png;base64,{}", &base64::encode(&png)))
     }
 
-    /// Generate a string representation of the glyph mapping
-    /// and the substitute character index
-    fn glyph_mapping(&self) -> (String, usize) {
-        let mut st = String::new();
-        // group codepoints in ranges of subsequent codepoints
+    /// Group the code points in ranges of subsequent code points,
+    /// and find the substitute character index
+    fn glyph_ranges(&self) -> (Vec<(u32, u32)>, usize) {
+        let mut ranges = Vec::new();
         let mut start = self.glyph_code_points[0];
         let mut last = self.glyph_code_points[0];
         let mut substitute_index = 0;
@@ -374,19 +399,26 @@ impl FontData {
             if code_point == last + 1 {
                 last = code_point;
             } else {
-                if start == last {
-                    st.push_str(&format!("\\u{{{:x}}}", start));
-                } else {
-                    st.push_str(&format!("\\0\\u{{{:x}}}\\u{{{:x}}}", start, last));
-                }
+                ranges.push((start, last));
                 start = code_point;
                 last = code_point;
             }
         }
-        if start == last {
-            st.push_str(&format!("\\u{{{:x}}}", start));
-        } else {
-            st.push_str(&format!("\\0\\u{{{:x}}}\\u{{{:x}}}", start, last));
+        ranges.push((start, last));
+        (ranges, substitute_index)
+    }
+
+    /// Generate a string representation of the glyph mapping
+    /// and the substitute character index
+    fn glyph_mapping(&self) -> (String, usize) {
+        let (ranges, substitute_index) = self.glyph_ranges();
+        let mut st = String::new();
+        for (start, last) in ranges {
+            if start == last {
+                st.push_str(&format!("\\u{{{:x}}}", start));
+            } else {
+                st.push_str(&format!("\\0\\u{{{:x}}}\\u{{{:x}}}", start, last));
+            }
         }
         (st, substitute_index)
     }
@@ -443,25 +475,123 @@ impl FontData {
         st
     }
 
+    /// Save the charset in the binary format, that can be loaded at runtime
+    /// with `Charset::from_bytes`
+    fn save_binary_font<P: AsRef<Path>>(&self, binary_file: &P) -> Result<(), Box<dyn Error>> {
+        fn push_section(file: &mut Vec<u8>, section: &[u8]) -> Result<(), Box<dyn Error>> {
+            file.extend_from_slice(&u32::try_from(section.len())?.to_le_bytes());
+            file.extend_from_slice(section);
+            Ok(())
+        }
+
+        let (ranges, substitute_index) = self.glyph_ranges();
+        let mut glyph_mapping = String::new();
+        for (start, last) in ranges {
+            let start = char::from_u32(start).ok_or("invalid code point")?;
+            let last = char::from_u32(last).ok_or("invalid code point")?;
+            if start != last {
+                glyph_mapping.push('\0');
+                glyph_mapping.push(start);
+            }
+            glyph_mapping.push(last);
+        }
+
+        let mut ligatures = String::new();
+        for code_points in self.ligature_code_points.iter() {
+            ligatures.push('\0');
+            for code_point in code_points {
+                ligatures.push(char::from_u32(*code_point).ok_or("invalid code point")?);
+            }
+        }
+
+        let mut side_bearings = Vec::new();
+        for (glyph, left_bearing, right_bearing) in self.glyph_bearings.iter() {
+            side_bearings.extend_from_slice(&u16::try_from(*glyph)?.to_le_bytes());
+            side_bearings.extend_from_slice(&[*left_bearing as u8, *right_bearing as u8]);
+        }
+
+        let mut kerning_pairs = Vec::new();
+        for (left_class, right_class, kerning) in self.kering_pairs.iter() {
+            kerning_pairs.extend_from_slice(&[*left_class, *right_class, *kerning as u8]);
+        }
+
+        let mut kerning_overrides = Vec::new();
+        for (left, right, kerning) in self.kerning_overrides.iter() {
+            kerning_overrides.extend_from_slice(&u16::try_from(*left)?.to_le_bytes());
+            kerning_overrides.extend_from_slice(&u16::try_from(*right)?.to_le_bytes());
+            kerning_overrides.push(*kerning as u8);
+        }
+
+        let mut file = Vec::new();
+        file.extend_from_slice(b"MOGF");
+        file.extend_from_slice(&[
+            BINARY_FORMAT_VERSION,
+            u8::try_from(self.line_height)?,
+            BASELINE as u8,
+            UNDERLINE_OFFSET as u8,
+            X_HEIGHT as u8,
+            self.default_bearings.0 as u8,
+            self.default_bearings.1 as u8,
+            match self.glyph_format() {
+                GlyphFormat::Compact => 0,
+                GlyphFormat::Wide => 1,
+            },
+        ]);
+        file.extend_from_slice(&(ATLAS_WIDTH as u16).to_le_bytes());
+        file.extend_from_slice(&u16::try_from(substitute_index)?.to_le_bytes());
+        file.extend_from_slice(&u16::try_from(self.glyph_code_points.len())?.to_le_bytes());
+        push_section(&mut file, glyph_mapping.as_bytes())?;
+        push_section(&mut file, ligatures.as_bytes())?;
+        push_section(&mut file, &self.glyph_data())?;
+        push_section(&mut file, &side_bearings)?;
+        push_section(&mut file, &kerning_pairs)?;
+        push_section(&mut file, &kerning_overrides)?;
+        push_section(&mut file, &self.bitmap_data()?)?;
+        std::fs::write(binary_file, &file)?;
+        Ok(())
+    }
+
     fn save_raw_font<P: AsRef<Path>>(&self, raw_file: &P) -> Result<(), Box<dyn Error>> {
         std::fs::write(raw_file, &self.bitmap_data()?)?;
         Ok(())
     }
 
-    fn save_raw_glyph_data<P: AsRef<Path>>(&self, raw_file: &P) -> std::io::Result<()> {
+    /// Choose the compact glyph format, unless some glyphs don't fit into it
+    fn glyph_format(&self) -> GlyphFormat {
+        let fits_compact = self.glyphs.iter().all(|glyph| {
+            glyph.left < 256 && glyph.top < 256 && glyph.img.width() < 16 && glyph.img.height() < 16
+        });
+        if fits_compact {
+            GlyphFormat::Compact
+        } else {
+            GlyphFormat::Wide
+        }
+    }
+
+    fn glyph_data(&self) -> Vec<u8> {
+        let glyph_format = self.glyph_format();
         let mut glyph_data = Vec::new();
         for glyph in self.glyphs.iter() {
-            // concat width and height into a single u8
-            let dimensions = (glyph.img.height() as u8) << 4 | (glyph.img.width() as u8);
-            glyph_data.extend_from_slice(&[
-                glyph.left as u8,
-                glyph.top as u8,
-                dimensions,
-                glyph.left_kerning_class,
-                glyph.right_kerning_class,
-            ]);
+            match glyph_format {
+                GlyphFormat::Compact => {
+                    // concat width and height into a single u8
+                    let dimensions = (glyph.img.height() as u8) << 4 | (glyph.img.width() as u8);
+                    glyph_data.extend_from_slice(&[glyph.left as u8, glyph.top as u8, dimensions]);
+                }
+                GlyphFormat::Wide => {
+                    glyph_data.extend_from_slice(&(glyph.left as u16).to_le_bytes());
+                    glyph_data.extend_from_slice(&(glyph.top as u16).to_le_bytes());
+                    glyph_data
+                        .extend_from_slice(&[glyph.img.width() as u8, glyph.img.height() as u8]);
+                }
+            }
+            glyph_data.extend_from_slice(&[glyph.left_kerning_class, glyph.right_kerning_class]);
         }
-        std::fs::write(raw_file, &glyph_data)
+        glyph_data
+    }
+
+    fn save_raw_glyph_data<P: AsRef<Path>>(&self, raw_file: &P) -> std::io::Result<()> {
+        std::fs::write(raw_file, self.glyph_data())
     }
 
     pub fn write<P: AsRef<Path>>(
@@ -501,6 +631,7 @@ impl FontData {
         let kerning_overrides = self.kerning_overrides();
 
         let charset_upper = format!("{}", self.charset).to_uppercase();
+        let glyph_format = self.glyph_format();
 
         writeln!(
             file,
@@ -515,6 +646,7 @@ pub const {charset_upper}: Charset = Charset {{
         {substitute_index},
     ),
     glyph_data: include_bytes!("{relative_glyphs_path}"),
+    glyph_format: GlyphFormat::{glyph_format:?},
     side_bearings: SideBearings::new(
         &[{side_bearings}],
         {default_bearings},
@@ -528,7 +660,9 @@ pub const {charset_upper}: Charset = Charset {{
         {ligature_offset},
     ),
     line_height: {line_height},
-    baseline: 8,
+    baseline: {BASELINE},
+    underline_offset: {UNDERLINE_OFFSET},
+    x_height: {X_HEIGHT},
 }};"#,
         )?;
 
@@ -536,6 +670,41 @@ pub const {charset_upper}: Charset = Charset {{
     }
 }
 
+/// Top left corners of the glyphs in the atlas, and the atlas height
+type Packing = (Vec<(u32, u32)>, u32);
+
+/// Pack the glyphs of the given dimensions into rows of the atlas,
+/// returning the top left corner of every glyph and the atlas height
+fn pack_glyphs(
+    dimensions: impl IntoIterator<Item = (u32, u32)>,
+    line_height: u32,
+) -> Result<Packing, Box<dyn Error>> {
+    let mut positions = Vec::new();
+    let mut left = 0;
+    let mut top = 0;
+    // Rows are at least one line high, and grow to fit taller glyphs
+    let mut row_height = line_height;
+    // 1 pixel spacing between glyphs is for pure aesthetic reasons
+    let spacing = 1;
+    for (width, height) in dimensions {
+        if width > ATLAS_WIDTH {
+            return Err(format!(
+                "Glyph is {width}px wide, that doesn't fit the {ATLAS_WIDTH}px wide atlas"
+            )
+            .into());
+        }
+        if left + width > ATLAS_WIDTH {
+            left = 0;
+            top += row_height + spacing;
+            row_height = line_height;
+        }
+        positions.push((left, top));
+        row_height = row_height.max(height);
+        left += width + spacing;
+    }
+    Ok((positions, top + row_height))
+}
+
 fn update_specimen<P: AsRef<Path>>(file: &P, fonts: &[FontData]) -> Result<(), Box<dyn Error>> {
     let input = std::fs::read_to_string(file)?;
     let mut output = Vec::new();
@@ -565,3 +734,83 @@ fn update_specimen<P: AsRef<Path>>(file: &P, fonts: &[FontData]) -> Result<(), B
     std::fs::write(file, output.join("\n"))?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn font_data(images: Vec<image::GrayImage>) -> Result<FontData, Box<dyn Error>> {
+        let line_height = 11;
+        let (positions, atlas_height) =
+            pack_glyphs(images.iter().map(|img| img.dimensions()), line_height)?;
+        Ok(FontData {
+            charset: Charset::ASCII,
+            glyph_code_points: Vec::new(),
+            ligature_code_points: Vec::new(),
+            glyphs: images
+                .into_iter()
+                .zip(positions)
+                .map(|(img, (left, top))| Glyph {
+                    left,
+                    top,
+                    left_kerning_class: 0,
+                    right_kerning_class: 0,
+                    img,
+                })
+                .collect(),
+            atlas_height,
+            line_height,
+            glyph_bearings: Vec::new(),
+            default_bearings: (0, 0),
+            kerning_overrides: Vec::new(),
+            kering_pairs: Vec::new(),
+        })
+    }
+
+    /// A glyph with a checkerboard pattern, so that overlaps are detected
+    fn glyph(width: u32, height: u32) -> image::GrayImage {
+        image::GrayImage::from_fn(width, height, |x, y| {
+            image::Luma::from([((x + y) % 2) as u8 * 255])
+        })
+    }
+
+    fn is_set(bitmap: &[u8], x: u32, y: u32) -> bool {
+        bitmap[(x / 8 + y * (ATLAS_WIDTH / 8)) as usize] & (0x80 >> (x % 8)) != 0
+    }
+
+    #[test]
+    fn large_glyphs_round_trip_through_the_atlas() {
+        let images = vec![
+            glyph(60, 5),
+            glyph(60, 20),
+            glyph(30, 11),
+            glyph(16, 16),
+            glyph(ATLAS_WIDTH, 3),
+        ];
+        let font = font_data(images).unwrap();
+        let positions: Vec<_> = font
+            .glyphs
+            .iter()
+            .map(|glyph| (glyph.left, glyph.top))
+            .collect();
+        assert_eq!(positions, vec![(0, 0), (61, 0), (0, 21), (31, 21), (0, 38)]);
+        assert_eq!(font.atlas_height, 49);
+
+        let bitmap = font.bitmap_data().unwrap();
+        for glyph in font.glyphs.iter() {
+            for y in 0..glyph.img.height() {
+                for x in 0..glyph.img.width() {
+                    assert_eq!(
+                        is_set(&bitmap, glyph.left + x, glyph.top + y),
+                        glyph.img.get_pixel(x, y).0[0] == 0
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn glyphs_wider_than_the_atlas_are_an_error() {
+        assert!(font_data(vec![glyph(ATLAS_WIDTH + 1, 11)]).is_err());
+    }
+}
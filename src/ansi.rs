@@ -0,0 +1,415 @@
+use crate::{
+    run_style::{RunStyle, Runs},
+    text_style::{SpanStyle, TextStyle},
+};
+use embedded_graphics::{pixelcolor::PixelColor, text::DecorationColor};
+
+/// The escape character, that starts the escape sequences.
+const ESCAPE: char = '\x1b';
+
+/// A parser of the ANSI escape sequences, that splits a text into styled runs.
+///
+/// The SGR (Select Graphic Rendition) sequences like `\x1b[1;31m` set the foreground
+/// and background colors from the palette, bold, underline, strikethrough and reverse video.
+/// The palette has the 8 standard colors followed by the 8 bright colors,
+/// the colors missing from it are ignored. The other escape sequences are removed.
+///
+/// The reverse video swaps the text color with the background color. Without a background
+/// color, e.g. on a monochrome display, the text is drawn with the first palette color,
+/// that is black in the terminals, on the background of the text color.
+#[derive(Clone, Debug)]
+pub struct Ansi<'a, C> {
+    text: &'a str,
+    initial_style: SpanStyle<C>,
+    style: SpanStyle<C>,
+    reverse: bool,
+    palette: &'a [C],
+}
+
+impl<'a, C> Ansi<'a, C>
+where
+    C: Copy,
+{
+    /// Creates a new parser of the text with the initial style and the palette.
+    pub fn new(text: &'a str, style: SpanStyle<C>, palette: &'a [C]) -> Self {
+        Self {
+            text,
+            initial_style: style,
+            style,
+            reverse: false,
+            palette,
+        }
+    }
+
+    /// Removes the escape sequence at the start of the text,
+    /// and applies it if it is an SGR sequence.
+    fn escape(&mut self) {
+        let bytes = self.text.as_bytes();
+        if bytes.get(1) != Some(&b'[') {
+            // Only the control sequences are supported, the other escapes are dropped.
+            self.text = &self.text[ESCAPE.len_utf8()..];
+            return;
+        }
+        // The parameter bytes, followed by the intermediate bytes and the final byte.
+        let params_end = 2 + bytes[2..]
+            .iter()
+            .take_while(|byte| (0x30..=0x3f).contains(*byte))
+            .count();
+        let intermediate_end = params_end
+            + bytes[params_end..]
+                .iter()
+                .take_while(|byte| (0x20..=0x2f).contains(*byte))
+                .count();
+        let params = &self.text[2..params_end];
+        match bytes.get(intermediate_end) {
+            Some(&byte) if (0x40..=0x7e).contains(&byte) => {
+                if byte == b'm' && intermediate_end == params_end {
+                    self.select_graphic_rendition(params);
+                }
+                self.text = &self.text[intermediate_end + 1..];
+            }
+            // An incomplete sequence is dropped.
+            _ => self.text = &self.text[intermediate_end..],
+        }
+    }
+
+    /// Applies the parameters of an SGR sequence.
+    fn select_graphic_rendition(&mut self, params: &str) {
+        let initial_style = self.initial_style;
+        // An empty parameter is 0, and the parameters that don't fit are ignored.
+        let mut params = params.split(';').filter_map(|param| match param {
+            "" => Some(0),
+            _ => param.parse().ok(),
+        });
+        while let Some(param) = params.next() {
+            match param {
+                0 => (self.style, self.reverse) = (initial_style, false),
+                1 => self.style.bold = true,
+                22 => self.style.bold = initial_style.bold,
+                4 => self.style.underline_color = DecorationColor::TextColor,
+                24 => self.style.underline_color = initial_style.underline_color,
+                7 => self.reverse = true,
+                27 => self.reverse = false,
+                9 => self.style.strikethrough_color = DecorationColor::TextColor,
+                29 => self.style.strikethrough_color = initial_style.strikethrough_color,
+                30..=37 => self.set_foreground(self.color(param - 30)),
+                90..=97 => self.set_foreground(self.color(param - 90 + 8)),
+                39 => self.style.text_color = initial_style.text_color,
+                40..=47 => self.set_background(self.color(param - 40)),
+                100..=107 => self.set_background(self.color(param - 100 + 8)),
+                49 => self.style.background_color = initial_style.background_color,
+                // The extended colors are only supported from the palette.
+                38 | 48 => {
+                    let color = match params.next() {
+                        Some(5) => params.next().and_then(|index| self.color(index)),
+                        Some(2) => {
+                            // Skip the red, green and blue components.
+                            params.nth(2);
+                            None
+                        }
+                        _ => None,
+                    };
+                    match param {
+                        38 => self.set_foreground(color),
+                        _ => self.set_background(color),
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Returns the palette color.
+    fn color(&self, index: u16) -> Option<C> {
+        self.palette.get(index as usize).copied()
+    }
+
+    /// Sets the foreground color, if it is in the palette.
+    fn set_foreground(&mut self, color: Option<C>) {
+        if let Some(color) = color {
+            self.style.text_color = Some(color);
+        }
+    }
+
+    /// Sets the background color, if it is in the palette.
+    fn set_background(&mut self, color: Option<C>) {
+        if let Some(color) = color {
+            self.style.background_color = Some(color);
+        }
+    }
+}
+
+impl<'a, C> Iterator for Ansi<'a, C>
+where
+    C: Copy,
+{
+    type Item = (&'a str, SpanStyle<C>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.text.starts_with(ESCAPE) {
+            self.escape();
+        }
+        if self.text.is_empty() {
+            return None;
+        }
+        let end = self.text.find(ESCAPE).unwrap_or(self.text.len());
+        let (run, rest) = self.text.split_at(end);
+        self.text = rest;
+        let mut style = self.style;
+        if self.reverse {
+            // The transparent background is reversed to the black from the palette,
+            // without it the text color is kept, so that the text doesn't disappear.
+            let background_color = self.style.background_color.or_else(|| self.color(0));
+            if let Some(background_color) = background_color {
+                style.text_color = Some(background_color);
+                style.background_color = self.style.text_color;
+            }
+        }
+        Some((run, style))
+    }
+}
+
+/// A text renderer for the text with the ANSI escape sequences, e.g. the serial console logs.
+///
+/// The escape sequences are parsed by [`Ansi`], and are not included in the measured text.
+/// They are parsed separately in every string passed to the renderer, so the style is reset
+/// at the start of every line of a [`Text`](embedded_graphics::text::Text),
+/// and every word of a `TextBox`. To keep the style across the words and the lines,
+/// wrap the whole text with [`Paragraph::draw_runs`](crate::Paragraph::draw_runs),
+/// e.g. `paragraph.draw_runs(ansi_style.ansi(log), position, display)`.
+pub type AnsiStyle<'a, C> = RunStyle<C, &'a [C]>;
+
+impl<'a, C> AnsiStyle<'a, C>
+where
+    C: PixelColor,
+{
+    /// Creates a new ANSI renderer with the text style and the palette,
+    /// that has the 8 standard colors followed by the 8 bright colors.
+    pub fn new(style: TextStyle<C>, palette: &'a [C]) -> Self {
+        Self::with_runs(style, palette)
+    }
+
+    /// Returns the parser of the escape sequences in the text.
+    pub fn ansi<'t>(&'t self, text: &'t str) -> Ansi<'t, C> {
+        self.runs(text)
+    }
+}
+
+impl<C> Runs<C> for &[C]
+where
+    C: Copy,
+{
+    type Iter<'t>
+        = Ansi<'t, C>
+    where
+        Self: 't;
+
+    fn runs<'t>(&'t self, text: &'t str, style: SpanStyle<C>) -> Self::Iter<'t> {
+        Ansi::new(text, style, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::Paragraph;
+    use embedded_graphics::{
+        geometry::Point,
+        mock_display::MockDisplay,
+        pixelcolor::{BinaryColor, Rgb888, RgbColor},
+        text::{
+            renderer::{CharacterStyle, TextRenderer},
+            Baseline,
+        },
+    };
+    use std::vec::Vec;
+
+    const PALETTE: [Rgb888; 8] = [
+        Rgb888::BLACK,
+        Rgb888::RED,
+        Rgb888::GREEN,
+        Rgb888::YELLOW,
+        Rgb888::BLUE,
+        Rgb888::MAGENTA,
+        Rgb888::CYAN,
+        Rgb888::WHITE,
+    ];
+
+    #[test]
+    fn test_parse_sgr() {
+        let style = SpanStyle::new(Rgb888::WHITE);
+        let text = "\x1b[1;31mERROR\x1b[0m: \x1b[4mretry\x1b[24m in \x1b[7m5s\x1b[44m!";
+        let runs: Vec<_> = Ansi::new(text, style, &PALETTE).collect();
+        let mut error = SpanStyle::new(Rgb888::RED);
+        error.bold = true;
+        let mut underlined = style;
+        underlined.underline_color = DecorationColor::TextColor;
+        // The transparent background is reversed to the black from the palette.
+        let reversed = SpanStyle {
+            background_color: Some(Rgb888::WHITE),
+            ..SpanStyle::new(Rgb888::BLACK)
+        };
+        let reversed_on_blue = SpanStyle {
+            background_color: Some(Rgb888::WHITE),
+            ..SpanStyle::new(Rgb888::BLUE)
+        };
+        assert_eq!(
+            runs,
+            [
+                ("ERROR", error),
+                (": ", style),
+                ("retry", underlined),
+                (" in ", style),
+                ("5s", reversed),
+                ("!", reversed_on_blue)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_params() {
+        let style = SpanStyle::new(Rgb888::WHITE);
+        // The parameters that don't fit are ignored, and only an empty one resets the style.
+        let text = "\x1b[31;99999;?ma\x1b[32;mb";
+        let runs: Vec<_> = Ansi::new(text, style, &PALETTE).collect();
+        assert_eq!(runs, [("a", SpanStyle::new(Rgb888::RED)), ("b", style)]);
+    }
+
+    #[test]
+    fn test_parse_colors() {
+        let style = SpanStyle::new(Rgb888::WHITE);
+        // The bright and the 24-bit colors are missing from the palette.
+        let text = "\x1b[32;44ma\x1b[39mb\x1b[91;38;2;1;2;3mc\x1b[38;5;3;49md";
+        let runs: Vec<_> = Ansi::new(text, style, &PALETTE).collect();
+        let mut green_on_blue = SpanStyle::new(Rgb888::GREEN);
+        green_on_blue.background_color = Some(Rgb888::BLUE);
+        let mut white_on_blue = style;
+        white_on_blue.background_color = Some(Rgb888::BLUE);
+        assert_eq!(
+            runs,
+            [
+                ("a", green_on_blue),
+                ("b", white_on_blue),
+                ("c", white_on_blue),
+                ("d", SpanStyle::new(Rgb888::YELLOW))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_other_escapes_are_removed() {
+        let style = SpanStyle::new(Rgb888::WHITE);
+        let text = "\x1b[2Ka\x1b[?25lb\x1bcc\x1b[1";
+        let runs: Vec<_> = Ansi::new(text, style, &PALETTE).collect();
+        assert_eq!(runs, [("a", style), ("b", style), ("cc", style)]);
+    }
+
+    #[test]
+    fn test_measure_ignores_escapes() {
+        let style = TextStyle::new(Rgb888::WHITE);
+        let ansi_style = AnsiStyle::new(style, &PALETTE);
+        assert_eq!(
+            ansi_style.measure_string("\x1b[31mH\x1b[0mi!\x1b[K", Point::zero(), Baseline::Top),
+            style.measure_string("Hi!", Point::zero(), Baseline::Top)
+        );
+    }
+
+    #[test]
+    fn test_draw_reverse_video() {
+        let mut style = TextStyle::new(Rgb888::WHITE);
+        style.set_background_color(Some(Rgb888::BLACK));
+        let ansi_style = AnsiStyle::new(style, &PALETTE);
+        let mut display = MockDisplay::new();
+        display.set_allow_overdraw(true);
+        ansi_style
+            .draw_string("\x1b[7mHi", Point::zero(), Baseline::Top, &mut display)
+            .unwrap();
+
+        let mut reversed = TextStyle::new(Rgb888::BLACK);
+        reversed.set_background_color(Some(Rgb888::WHITE));
+        let mut expected = MockDisplay::new();
+        expected.set_allow_overdraw(true);
+        reversed
+            .draw_string("Hi", Point::zero(), Baseline::Top, &mut expected)
+            .unwrap();
+        display.assert_eq(&expected);
+    }
+
+    #[test]
+    fn test_draw_reverse_video_without_background() {
+        let style = TextStyle::new(BinaryColor::On);
+        let palette = [BinaryColor::Off, BinaryColor::On];
+        let ansi_style = AnsiStyle::new(style, &palette);
+        let mut display = MockDisplay::new();
+        display.set_allow_overdraw(true);
+        ansi_style
+            .draw_string("\x1b[7mHi", Point::zero(), Baseline::Top, &mut display)
+            .unwrap();
+
+        // The monochrome text is inverted, like the selected item of a menu.
+        let mut reversed = TextStyle::new(BinaryColor::Off);
+        reversed.set_background_color(Some(BinaryColor::On));
+        let mut expected = MockDisplay::new();
+        expected.set_allow_overdraw(true);
+        reversed
+            .draw_string("Hi", Point::zero(), Baseline::Top, &mut expected)
+            .unwrap();
+        display.assert_eq(&expected);
+    }
+
+    #[test]
+    fn test_style_spans_words_and_lines_of_paragraph() {
+        let style = TextStyle::new(Rgb888::WHITE);
+        let ansi_style = AnsiStyle::new(style, &PALETTE);
+        let width = style.measure_string("ERROR:", Point::zero(), Baseline::Top);
+        let paragraph = Paragraph::new(style, width.next_position.x as u32);
+        let mut display = MockDisplay::new();
+        let text = "\x1b[31mERROR: disk\nfull\x1b[0m ok";
+        paragraph
+            .draw_runs(ansi_style.ansi(text), Point::zero(), &mut display)
+            .unwrap();
+
+        let mut red = style;
+        red.set_text_color(Some(Rgb888::RED));
+        let mut expected = MockDisplay::new();
+        let lines = [
+            (red, "ERROR:", 0, 0),
+            (red, "disk", 0, 11),
+            (red, "full", 0, 22),
+            (
+                style,
+                "ok",
+                red.measure_string("full ", Point::zero(), Baseline::Top)
+                    .next_position
+                    .x,
+                22,
+            ),
+        ];
+        for (style, text, x, y) in lines {
+            style
+                .draw_string(text, Point::new(x, y), Baseline::Top, &mut expected)
+                .unwrap();
+        }
+        display.assert_eq(&expected);
+    }
+
+    #[test]
+    fn test_style_is_reset_for_every_line() {
+        use embedded_graphics::{text::Text, Drawable};
+
+        let style = TextStyle::new(Rgb888::WHITE);
+        let ansi_style = AnsiStyle::new(style, &PALETTE);
+        let mut display = MockDisplay::new();
+        Text::new("\x1b[31mHi\nHi", Point::new(0, 8), ansi_style)
+            .draw(&mut display)
+            .unwrap();
+
+        let mut expected = MockDisplay::new();
+        Text::new("\x1b[31mHi\n\x1b[0mHi", Point::new(0, 8), ansi_style)
+            .draw(&mut expected)
+            .unwrap();
+        display.assert_eq(&expected);
+    }
+}
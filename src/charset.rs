@@ -1,98 +1,262 @@
-use crate::{kerning::Kerning, ligatures::Ligatures, side_bearings::SideBearings};
+use crate::{
+    font_file::{self, FontError},
+    kerning::Kerning,
+    ligatures::Ligatures,
+    side_bearings::SideBearings,
+};
 use embedded_graphics::{
     geometry::{Point, Size},
-    image::ImageRaw,
+    image::{GetPixel, ImageRaw},
     mono_font::mapping::{GlyphMapping, StrGlyphMapping},
     pixelcolor::BinaryColor,
     primitives::Rectangle,
 };
 
+/// A set of glyphs, together with the kerning tables,
+/// side bearings and ligatures that are used to lay them out.
+///
+/// Charsets are generated from the MogeeFont sources, like [`ASCII`](crate::ASCII),
+/// or loaded at runtime with [`Charset::from_bytes`].
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub struct Charset {
-    pub(crate) image: ImageRaw<'static, BinaryColor>,
-    pub(crate) glyph_mapping: StrGlyphMapping<'static>,
-    pub(crate) glyph_data: &'static [u8],
-    pub(crate) ligatures: Ligatures<'static>,
-    pub(crate) side_bearings: SideBearings<'static>,
-    pub(crate) kerning: Kerning<'static>,
+pub struct Charset<'a> {
+    pub(crate) image: ImageRaw<'a, BinaryColor>,
+    pub(crate) glyph_mapping: StrGlyphMapping<'a>,
+    pub(crate) glyph_data: &'a [u8],
+    pub(crate) glyph_format: GlyphFormat,
+    pub(crate) ligatures: Ligatures<'a>,
+    pub(crate) side_bearings: SideBearings<'a>,
+    pub(crate) kerning: Kerning<'a>,
     pub(crate) line_height: u32,
     pub(crate) baseline: u32,
+    pub(crate) underline_offset: u32,
+    pub(crate) x_height: u32,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub(crate) struct GlyphIndex(pub(crate) usize);
 
-impl Charset {
-    /// Returns an iterator over the glyph indices for the characters in a text.
-    /// Performs ligature substitution.
-    pub(crate) fn glyph_indices<'t>(
-        &'static self,
-        text: &'t str,
-    ) -> impl Iterator<Item = GlyphIndex> + 't {
-        // Iterate over the characters in the text and return the glyph index for each character
-        // the ligatures are handled by the ligature substitution.
-        let mut byte_offset = 0;
-        let mut chars = text.chars();
-        core::iter::from_fn(move || {
-            if let Some((liga_index, mut liga_size)) =
-                self.ligatures.substitute(&text[byte_offset..])
-            {
-                // Advance by the number of characters in the ligature
-                while liga_size > 0 {
-                    if let Some(glyph) = chars.next() {
-                        byte_offset += glyph.len_utf8();
-                    } else {
-                        return None;
-                    }
-                    liga_size -= 1;
-                }
-                return Some(GlyphIndex(liga_index));
-            } else if let Some(glyph) = chars.next() {
-                byte_offset += glyph.len_utf8();
-                return Some(self.glyph_index(glyph));
-            } else {
-                return None;
-            }
-        })
+/// The format of the glyph records in the glyph data.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum GlyphFormat {
+    /// 5 bytes per glyph: atlas x and y as u8, height and width packed in 4 bits each,
+    /// left and right kerning classes. Fits glyphs up to 15×15 pixels in a 256×256 atlas.
+    Compact,
+    /// 8 bytes per glyph: atlas x and y as u16, width and height as u8,
+    /// left and right kerning classes.
+    Wide,
+}
+
+impl GlyphFormat {
+    /// Returns the number of bytes in a glyph record.
+    pub(crate) const fn record_size(self) -> usize {
+        match self {
+            GlyphFormat::Compact => 5,
+            GlyphFormat::Wide => 8,
+        }
+    }
+}
+
+impl<'a> Charset<'a> {
+    /// Loads a charset from the MogeeFont binary format,
+    /// that can be generated with `generate-font --binary`.
+    ///
+    /// The data is validated, so that drawing with the charset doesn't panic,
+    /// and then used in place without copying. To draw text with the charset,
+    /// both the data and the charset need to be stored for the `'static` lifetime,
+    /// e.g. in a static cell after reading them from the external flash.
+    ///
+    /// ```
+    /// use embedded_graphics::{
+    ///     mock_display::MockDisplay, pixelcolor::BinaryColor, prelude::*, text::Text,
+    /// };
+    /// use embedded_mogeefont::{Charset, MogeeTextStyle};
+    ///
+    /// # fn read_font_from_flash() -> Vec<u8> {
+    /// #     let mut file = b"MOGF\x01\x03\x02\x00\x02\x00\x01\x00\x01\x00\x00\x00\x00\x00".to_vec();
+    /// #     let sections: [&[u8]; 7] = [b"#", b"", &[0, 0, 0x21, 0, 0], b"", b"", b"", &[0x80, 0x80]];
+    /// #     for section in sections {
+    /// #         file.extend_from_slice(&(section.len() as u32).to_le_bytes());
+    /// #         file.extend_from_slice(section);
+    /// #     }
+    /// #     file
+    /// # }
+    /// // With an allocator, the loaded font can be leaked, because it is used until the end.
+    /// // Without one, the data and the charset can be stored in a `static_cell::StaticCell`.
+    /// let data: &'static [u8] = Box::leak(read_font_from_flash().into_boxed_slice());
+    /// let charset: &'static Charset = Box::leak(Box::new(Charset::from_bytes(data).unwrap()));
+    ///
+    /// let style = MogeeTextStyle::with_charset(BinaryColor::On, charset);
+    /// let mut display = MockDisplay::new();
+    /// Text::new("##", Point::new(0, 2), style)
+    ///     .draw(&mut display)
+    ///     .unwrap();
+    /// display.assert_pattern(&["# #", "# #"]);
+    /// ```
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self, FontError> {
+        font_file::parse(data)
+    }
+
+    /// Returns the height of a line of text in pixels.
+    pub fn line_height(&self) -> u32 {
+        self.line_height
+    }
+
+    /// Returns the offset of the baseline from the top of the line in pixels.
+    pub fn baseline(&self) -> u32 {
+        self.baseline
+    }
+
+    /// Returns the number of glyphs in the charset, including the ligatures.
+    pub fn glyph_count(&self) -> usize {
+        self.glyph_data.len() / self.glyph_format.record_size()
+    }
+
+    /// Returns the image with all the glyphs of the charset.
+    pub fn image(&self) -> ImageRaw<'a, BinaryColor> {
+        self.image
+    }
+
+    /// Returns the glyph index for the start of a text,
+    /// together with the number of bytes that the glyph covers,
+    /// or `None` if the charset doesn't have a glyph for the first character.
+    /// Performs ligature substitution, if the ligatures are enabled.
+    pub(crate) fn glyph_at(&self, text: &str, ligatures: bool) -> Option<(GlyphIndex, usize)> {
+        if let Some((liga_index, liga_len)) =
+            ligatures.then(|| self.ligatures.substitute(text)).flatten()
+        {
+            Some((GlyphIndex(liga_index), liga_len))
+        } else {
+            let char = text.chars().next()?;
+            Some((self.try_glyph_index(char)?, char.len_utf8()))
+        }
+    }
+
+    /// Returns true if the charset has a glyph for a character.
+    pub(crate) fn contains(&self, char: char) -> bool {
+        self.try_glyph_index(char).is_some()
+    }
+
+    /// Returns the glyph data record of a glyph.
+    fn glyph_record(&self, glyph: GlyphIndex) -> &[u8] {
+        let size = self.glyph_format.record_size();
+        &self.glyph_data[glyph.0 * size..(glyph.0 + 1) * size]
     }
 
     /// Returns the area of a glyph in the font image.
     pub(crate) fn glyph_area(&self, glyph: GlyphIndex) -> Rectangle {
-        let start = glyph.0 * 5;
-        let end = start + 3;
-        if let [x, y, dimensions] = self.glyph_data[start..end] {
-            let width = (dimensions & 0x0F) as u32; // Lower 4 bits
-            let height = (dimensions >> 4) as u32; // Upper 4 bits
-            Rectangle::new(Point::new(x as i32, y as i32), Size::new(width, height))
-        } else {
-            Rectangle::zero()
+        match (self.glyph_format, self.glyph_record(glyph)) {
+            (GlyphFormat::Compact, [x, y, dimensions, ..]) => {
+                let width = (dimensions & 0x0F) as u32; // Lower 4 bits
+                let height = (dimensions >> 4) as u32; // Upper 4 bits
+                Rectangle::new(Point::new(*x as i32, *y as i32), Size::new(width, height))
+            }
+            (GlyphFormat::Wide, [x0, x1, y0, y1, width, height, ..]) => {
+                let x = u16::from_le_bytes([*x0, *x1]);
+                let y = u16::from_le_bytes([*y0, *y1]);
+                Rectangle::new(
+                    Point::new(x as i32, y as i32),
+                    Size::new(*width as u32, *height as u32),
+                )
+            }
+            _ => Rectangle::zero(),
         }
     }
 
+    /// Returns true if the glyph has a pixel set at the given point,
+    /// relative to the top left corner of the glyph.
+    pub(crate) fn is_ink(&self, glyph: GlyphIndex, point: Point) -> bool {
+        let area = self.glyph_area(glyph);
+        area.contains(area.top_left + point)
+            && self
+                .image
+                .pixel(area.top_left + point)
+                .is_some_and(|color| color.is_on())
+    }
+
     /// Returns the width of a glyph in the font image.
     pub(crate) fn glyph_width(&self, index: GlyphIndex) -> i32 {
-        (self.glyph_data[index.0 * 5 + 2] & 0x0F) as i32
+        let record = self.glyph_record(index);
+        match self.glyph_format {
+            GlyphFormat::Compact => (record[2] & 0x0F) as i32,
+            GlyphFormat::Wide => record[4] as i32,
+        }
     }
 
     /// Returns the spacing between two glyphs, or between the start of the text and a glyph.
-    /// Takes into account the side bearings and kerning.
-    pub(crate) fn spacing(&self, prev_glyph: Option<GlyphIndex>, next_glyph: GlyphIndex) -> i32 {
+    /// Takes into account the side bearings, and the kerning if it is enabled.
+    pub(crate) fn spacing(
+        &self,
+        prev_glyph: Option<GlyphIndex>,
+        next_glyph: GlyphIndex,
+        kerning: bool,
+    ) -> i32 {
         match prev_glyph {
             Some(prev) => {
                 let right_bearing = self.side_bearings.right(prev);
                 let left_bearing = self.side_bearings.left(next_glyph);
-                let kerning = self.kerning(prev, next_glyph).unwrap_or_default();
+                let kerning = match kerning {
+                    true => self.kerning(prev, next_glyph).unwrap_or_default(),
+                    false => 0,
+                };
                 right_bearing + kerning + left_bearing
             }
             None => self.side_bearings.left(next_glyph),
         }
     }
 
+    /// Returns the smallest left and right side bearings of the glyphs.
+    pub(crate) fn min_side_bearings(&self) -> (i32, i32) {
+        let bearings = &self.side_bearings.bearings;
+        (0..bearings.len()).map(|index| bearings.get(index)).fold(
+            (
+                self.side_bearings.default_left(),
+                self.side_bearings.default_right(),
+            ),
+            |(left, right), (_, left_bearing, right_bearing)| {
+                (left.min(left_bearing), right.min(right_bearing))
+            },
+        )
+    }
+
+    /// Returns the smallest kerning between two glyphs, or zero if all of it is positive.
+    pub(crate) fn min_kerning(&self) -> i32 {
+        let (pairs, overrides) = (&self.kerning.pairs, &self.kerning.overrides);
+        (0..pairs.len())
+            .map(|index| pairs.get(index).2)
+            .chain((0..overrides.len()).map(|index| overrides.get(index).2))
+            .fold(0, i32::min)
+    }
+
+    /// Returns the advance of the tabular digits, that fits the widest digit
+    /// together with the default side bearings.
+    pub(crate) fn tabular_advance(&self) -> i32 {
+        let max_width = ('0'..='9')
+            .filter(|&char| self.contains(char))
+            .map(|char| self.glyph_width(self.glyph_index(char)))
+            .max()
+            .unwrap_or_default();
+        self.side_bearings.default_left() + max_width + self.side_bearings.default_right()
+    }
+
+    /// Returns the vertical offset of the strikethrough from the top of the line,
+    /// the line crosses the middle of the lowercase letters.
+    pub(crate) fn strikethrough_offset(&self) -> u32 {
+        self.baseline - self.x_height.div_ceil(2)
+    }
+
     /// Returns the glyph index for a character.
-    fn glyph_index(&self, char: char) -> GlyphIndex {
+    pub(crate) fn glyph_index(&self, char: char) -> GlyphIndex {
         GlyphIndex(self.glyph_mapping.index(char))
     }
 
+    /// Returns the glyph index for a character, or `None` if the charset doesn't have it.
+    pub(crate) fn try_glyph_index(&self, char: char) -> Option<GlyphIndex> {
+        self.glyph_mapping
+            .ranges()
+            .find(|(_, range)| range.contains(&char))
+            .map(|(index, range)| GlyphIndex(index + (char as usize - *range.start() as usize)))
+    }
+
     /// Returns the kerning between two glyphs.
     fn kerning(&self, left: GlyphIndex, right: GlyphIndex) -> Option<i32> {
         self.kerning.kerning_override(left, right).or_else(|| {
@@ -104,24 +268,34 @@ impl Charset {
     }
 
     /// Returns the left kerning class for a glyph.
-    /// The left kerning class is stored in the upper 4 bits of the byte.
+    /// The left kerning class is stored in the second to last byte of the record.
     fn left_kerning_class(&self, index: GlyphIndex) -> u8 {
-        self.glyph_data[index.0 * 5 + 3]
+        let record = self.glyph_record(index);
+        record[record.len() - 2]
     }
 
     /// Returns the right kerning class for a glyph.
-    /// The right kerning class is stored in the lower 4 bits of the byte.
+    /// The right kerning class is stored in the last byte of the record.
     fn right_kerning_class(&self, index: GlyphIndex) -> u8 {
-        self.glyph_data[index.0 * 5 + 4]
+        let record = self.glyph_record(index);
+        record[record.len() - 1]
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::generated::ASCII;
+    use crate::{fallback_chain::FallbackChain, generated::ASCII};
     use embedded_graphics::{image::ImageDrawable, mock_display::MockDisplay};
 
+    #[test]
+    fn test_metrics() {
+        assert_eq!(ASCII.line_height(), 11);
+        assert_eq!(ASCII.baseline(), 8);
+        // 95 printable ASCII characters and 8 ligatures
+        assert_eq!(ASCII.glyph_count(), 103);
+    }
+
     #[test]
     fn test_glyph() {
         let area = ASCII.glyph_area(ASCII.glyph_index('a'));
@@ -142,6 +316,26 @@ mod tests {
         ]);
     }
 
+    #[test]
+    fn test_wide_glyph_area() {
+        let charset = Charset {
+            glyph_data: &[
+                0x2C, 0x01, 0x02, 0x00, 20, 17, 1, 2, // x: 300, y: 2, 20×17
+                0x00, 0x00, 0x13, 0x00, 3, 11, 3, 4, // x: 0, y: 19, 3×11
+            ],
+            glyph_format: GlyphFormat::Wide,
+            ..ASCII
+        };
+        assert_eq!(charset.glyph_count(), 2);
+        assert_eq!(
+            charset.glyph_area(GlyphIndex(0)),
+            Rectangle::new(Point::new(300, 2), Size::new(20, 17))
+        );
+        assert_eq!(charset.glyph_width(GlyphIndex(1)), 3);
+        assert_eq!(charset.left_kerning_class(GlyphIndex(1)), 3);
+        assert_eq!(charset.right_kerning_class(GlyphIndex(1)), 4);
+    }
+
     #[test]
     fn test_glyph_area() {
         assert_eq!(
@@ -159,10 +353,13 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::identity_op)]
     fn test_ligature_substitution_in_text() {
         let text = "虫ffifijjjssyj";
         let ligatures_offset = ASCII.ligatures.offset;
-        let mut glyphs = ASCII.glyph_indices(text);
+        let mut glyphs = FallbackChain::new(core::slice::from_ref(&ASCII))
+            .glyphs(text, true)
+            .map(|(_, glyph)| glyph.index);
         assert_eq!(glyphs.next(), Some(ASCII.glyph_index('虫')));
         assert_eq!(glyphs.next(), Some(GlyphIndex(ligatures_offset + 0))); // ffi
         assert_eq!(glyphs.next(), Some(GlyphIndex(ligatures_offset + 2))); // fi
@@ -173,10 +370,40 @@ mod tests {
         assert_eq!(glyphs.next(), None);
     }
 
+    #[test]
+    fn test_tabular_advance() {
+        let max_width = ('0'..='9')
+            .map(|char| ASCII.glyph_width(ASCII.glyph_index(char)))
+            .max()
+            .unwrap();
+        assert_eq!(ASCII.tabular_advance(), max_width + 1);
+    }
+
+    #[test]
+    fn test_disabled_ligatures() {
+        assert_eq!(
+            ASCII.glyph_at("fi", false),
+            Some((ASCII.glyph_index('f'), 1))
+        );
+        assert_eq!(
+            ASCII.glyph_at("fi", true),
+            Some((GlyphIndex(ASCII.ligatures.offset + 2), 2))
+        );
+        assert_eq!(ASCII.glyph_at("熊", true), None);
+    }
+
+    #[test]
+    fn test_try_glyph_index() {
+        for char in [' ', 'a', '~'] {
+            assert_eq!(ASCII.try_glyph_index(char), Some(ASCII.glyph_index(char)));
+        }
+        assert_eq!(ASCII.try_glyph_index('熊'), None);
+    }
+
     #[test]
     fn test_letter_spacing() {
         assert_eq!(
-            ASCII.spacing(Some(ASCII.glyph_index('o')), ASCII.glyph_index(',')),
+            ASCII.spacing(Some(ASCII.glyph_index('o')), ASCII.glyph_index(','), true),
             0
         );
         assert_eq!(
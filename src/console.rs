@@ -0,0 +1,366 @@
+use crate::text_style::TextStyle;
+use core::fmt;
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{Point, Size},
+    pixelcolor::PixelColor,
+    primitives::Rectangle,
+    text::{renderer::TextRenderer, Baseline},
+};
+
+/// A line of the console with a fixed capacity in bytes.
+#[derive(Clone, Debug)]
+struct ConsoleLine<const BYTES: usize> {
+    bytes: [u8; BYTES],
+    len: usize,
+    /// The line has changed since it was drawn.
+    dirty: bool,
+}
+
+impl<const BYTES: usize> ConsoleLine<BYTES> {
+    const EMPTY: Self = Self {
+        bytes: [0; BYTES],
+        len: 0,
+        dirty: true,
+    };
+
+    /// Returns the text of the line.
+    fn as_str(&self) -> &str {
+        // Only the whole characters are inserted, so the line is always valid UTF-8.
+        core::str::from_utf8(&self.bytes[..self.len]).unwrap_or_default()
+    }
+
+    /// Inserts a character at the byte offset, returns false if it doesn't fit.
+    fn insert(&mut self, offset: usize, char: char) -> bool {
+        let char_len = char.len_utf8();
+        if self.len + char_len > BYTES {
+            return false;
+        }
+        self.bytes.copy_within(offset..self.len, offset + char_len);
+        char.encode_utf8(&mut self.bytes[offset..offset + char_len]);
+        self.len += char_len;
+        self.dirty = true;
+        true
+    }
+
+    /// Removes the character at the byte offset, and returns it.
+    fn remove(&mut self, offset: usize) -> Option<char> {
+        let char = self.as_str()[offset..].chars().next()?;
+        self.bytes
+            .copy_within(offset + char.len_utf8()..self.len, offset);
+        self.len -= char.len_utf8();
+        self.dirty = true;
+        Some(char)
+    }
+}
+
+/// A scrolling text console, that keeps the last `LINES` lines of up to `BYTES` bytes each.
+///
+/// The text is written with [`core::fmt::Write`], and wrapped between the characters
+/// when it doesn't fit into the width. When all the lines are used, the console scrolls up.
+/// The control characters are interpreted like in a terminal:
+/// `\n` starts a new line, `\r` returns to the start of the line, so the following
+/// characters overwrite it, `\t` advances to the next tab stop,
+/// and backspace removes the previous character.
+#[derive(Clone, Debug)]
+pub struct Console<C, const LINES: usize, const BYTES: usize> {
+    style: TextStyle<C>,
+    width: u32,
+    background_color: C,
+    tab_width: u32,
+    lines: [ConsoleLine<BYTES>; LINES],
+    /// Index of the top line in the ring buffer.
+    first: usize,
+    /// Number of the used lines.
+    count: usize,
+    /// Byte offset of the cursor in the last line.
+    cursor: usize,
+    /// The console has scrolled or was cleared since it was drawn.
+    redraw: bool,
+}
+
+impl<C, const LINES: usize, const BYTES: usize> Console<C, LINES, BYTES>
+where
+    C: PixelColor,
+{
+    /// Creates a new console with the text style, the width in pixels,
+    /// and the background color, that the changed lines are cleared with.
+    ///
+    /// The tab stops are four spaces apart.
+    pub fn new(style: TextStyle<C>, width: u32, background_color: C) -> Self {
+        assert!(LINES > 0, "the console must have at least one line");
+        let space_width = style
+            .measure_string(" ", Point::zero(), Baseline::Top)
+            .next_position
+            .x;
+        Self {
+            style,
+            width,
+            background_color,
+            tab_width: 4 * space_width.max(1) as u32,
+            lines: [ConsoleLine::EMPTY; LINES],
+            first: 0,
+            count: 1,
+            cursor: 0,
+            redraw: true,
+        }
+    }
+
+    /// Sets the distance between the tab stops in pixels.
+    pub fn set_tab_width(&mut self, tab_width: u32) {
+        self.tab_width = tab_width.max(1);
+        self.redraw = true;
+    }
+
+    /// Returns the size of the console in pixels.
+    pub fn size(&self) -> Size {
+        Size::new(self.width, LINES as u32 * self.style.line_height())
+    }
+
+    /// Returns an iterator over the lines from the top to the bottom.
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        (0..self.count).map(|row| self.lines[(self.first + row) % LINES].as_str())
+    }
+
+    /// Removes all the lines.
+    pub fn clear(&mut self) {
+        self.lines = [ConsoleLine::EMPTY; LINES];
+        self.first = 0;
+        self.count = 1;
+        self.cursor = 0;
+        self.redraw = true;
+    }
+
+    /// Draws the lines, that have changed since the last call, with the top left corner
+    /// of the console at the position.
+    pub fn draw<D>(&mut self, position: Point, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let line_height = self.style.line_height();
+        for row in 0..LINES {
+            let line = &self.lines[(self.first + row) % LINES];
+            if !self.redraw && !line.dirty {
+                continue;
+            }
+            let top_left = position + Point::new(0, (row as u32 * line_height) as i32);
+            let area = Rectangle::new(top_left, Size::new(self.width, line_height));
+            target.fill_solid(&area, self.background_color)?;
+            if row < self.count {
+                let mut x = 0;
+                for (index, segment) in line.as_str().split('\t').enumerate() {
+                    if index > 0 {
+                        x = self.tab_stop(x);
+                    }
+                    let position = top_left + Point::new(x, 0);
+                    x = self
+                        .style
+                        .draw_string(segment, position, Baseline::Top, target)?
+                        .x
+                        - top_left.x;
+                }
+            }
+        }
+        for line in self.lines.iter_mut() {
+            line.dirty = false;
+        }
+        self.redraw = false;
+        Ok(())
+    }
+
+    /// Returns the width of a line of text in pixels, including the tabs.
+    fn text_width(&self, text: &str) -> i32 {
+        let mut x = 0;
+        for (index, segment) in text.split('\t').enumerate() {
+            if index > 0 {
+                x = self.tab_stop(x);
+            }
+            x = self.style.advance_position(segment, x);
+        }
+        x
+    }
+
+    /// Returns the position of the next tab stop after the horizontal offset.
+    fn tab_stop(&self, x: i32) -> i32 {
+        let tab_width = self.tab_width as i32;
+        (x.div_euclid(tab_width) + 1) * tab_width
+    }
+
+    /// Returns the index of the last line in the ring buffer.
+    fn last(&self) -> usize {
+        (self.first + self.count - 1) % LINES
+    }
+
+    /// Starts a new line, and scrolls up if all the lines are used.
+    fn new_line(&mut self) {
+        if self.count < LINES {
+            self.count += 1;
+        } else {
+            self.first = (self.first + 1) % LINES;
+            self.redraw = true;
+        }
+        let last = self.last();
+        self.lines[last] = ConsoleLine::EMPTY;
+        self.cursor = 0;
+    }
+
+    /// Writes a printable character at the cursor, and wraps it to a new line
+    /// if it doesn't fit into the width.
+    fn write_printable(&mut self, char: char) {
+        let (last, cursor) = (self.last(), self.cursor);
+        let overwritten = self.lines[last].remove(cursor);
+        if self.lines[last].insert(cursor, char) {
+            let fits = self.text_width(self.lines[last].as_str()) <= self.width as i32;
+            // A character, that is wider than the console, still gets its own line.
+            if fits || cursor == 0 {
+                self.cursor += char.len_utf8();
+                return;
+            }
+            self.lines[last].remove(cursor);
+        }
+        if let Some(overwritten) = overwritten {
+            self.lines[last].insert(cursor, overwritten);
+        }
+        // The character can't fit even into an empty line.
+        if cursor == 0 {
+            return;
+        }
+        self.new_line();
+        self.write_printable(char);
+    }
+
+    /// Removes the character before the cursor.
+    fn backspace(&mut self) {
+        let last = self.last();
+        if let Some(char) = self.lines[last].as_str()[..self.cursor].chars().next_back() {
+            self.cursor -= char.len_utf8();
+            self.lines[last].remove(self.cursor);
+        }
+    }
+}
+
+impl<C, const LINES: usize, const BYTES: usize> fmt::Write for Console<C, LINES, BYTES>
+where
+    C: PixelColor,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for char in s.chars() {
+            self.write_char(char)?;
+        }
+        Ok(())
+    }
+
+    fn write_char(&mut self, char: char) -> fmt::Result {
+        match char {
+            '\n' => self.new_line(),
+            '\r' => self.cursor = 0,
+            '\x08' => self.backspace(),
+            '\t' => self.write_printable(char),
+            char if char.is_control() => {}
+            char => self.write_printable(char),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fmt::Write;
+    use embedded_graphics::{mock_display::MockDisplay, pixelcolor::BinaryColor};
+
+    fn console<const LINES: usize>(width: u32) -> Console<BinaryColor, LINES, 32> {
+        Console::new(TextStyle::new(BinaryColor::On), width, BinaryColor::Off)
+    }
+
+    fn assert_lines<const LINES: usize>(console: &Console<BinaryColor, LINES, 32>, lines: &[&str]) {
+        extern crate std;
+        use std::vec::Vec;
+
+        assert_eq!(console.lines().collect::<Vec<_>>(), lines);
+    }
+
+    #[test]
+    fn test_new_lines_and_scrolling() {
+        let mut console = console::<2>(64);
+        write!(console, "one\ntwo").unwrap();
+        assert_lines(&console, &["one", "two"]);
+        write!(console, "\nthree\n").unwrap();
+        assert_lines(&console, &["three", ""]);
+    }
+
+    #[test]
+    fn test_wrapping() {
+        let style = TextStyle::new(BinaryColor::On);
+        let width = style.advance_position("Hello", 0) as u32;
+        let mut console = console::<3>(width);
+        write!(console, "Hello, world").unwrap();
+        assert_lines(&console, &["Hello", ", wo", "rld"]);
+    }
+
+    #[test]
+    fn test_line_capacity() {
+        let mut console: Console<BinaryColor, 2, 4> =
+            Console::new(TextStyle::new(BinaryColor::On), 128, BinaryColor::Off);
+        write!(console, "abcdef").unwrap();
+        assert!(console.lines().eq(["abcd", "ef"]));
+    }
+
+    #[test]
+    fn test_carriage_return_and_backspace() {
+        let mut console = console::<2>(64);
+        write!(console, "10%\r50").unwrap();
+        assert_lines(&console, &["50%"]);
+        write!(console, "\nabc\x08\x08d").unwrap();
+        assert_lines(&console, &["50%", "ad"]);
+        write!(console, "\x08\x08\x08").unwrap();
+        assert_lines(&console, &["50%", ""]);
+    }
+
+    #[test]
+    fn test_tab_stops() {
+        let mut console = console::<1>(64);
+        console.set_tab_width(10);
+        assert_eq!(console.text_width("\t"), 10);
+        assert_eq!(console.text_width("a\tb"), 10 + console.text_width("b"));
+        assert_eq!(console.text_width("\t\t"), 20);
+        write!(console, "a\tb").unwrap();
+        assert_lines(&console, &["a\tb"]);
+    }
+
+    #[test]
+    fn test_redraw_only_changed_lines() {
+        let mut console = console::<3>(32);
+        let line_height = console.style.line_height();
+        write!(console, "one\ntwo").unwrap();
+        let mut display = MockDisplay::new();
+        display.set_allow_overdraw(true);
+        console.draw(Point::zero(), &mut display).unwrap();
+        assert_eq!(
+            display.affected_area(),
+            Rectangle::new(Point::zero(), console.size())
+        );
+
+        write!(console, "!").unwrap();
+        let mut display = MockDisplay::new();
+        display.set_allow_overdraw(true);
+        console.draw(Point::zero(), &mut display).unwrap();
+        assert_eq!(
+            display.affected_area(),
+            Rectangle::new(
+                Point::new(0, line_height as i32),
+                Size::new(32, line_height)
+            )
+        );
+
+        // Scrolling moves all the lines.
+        write!(console, "\n\n").unwrap();
+        let mut display = MockDisplay::new();
+        display.set_allow_overdraw(true);
+        console.draw(Point::zero(), &mut display).unwrap();
+        assert_eq!(
+            display.affected_area(),
+            Rectangle::new(Point::zero(), console.size())
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use crate::charset::{Charset, GlyphIndex};
+use embedded_graphics::{geometry::Point, primitives::Rectangle};
+
+/// A chain of charsets, where every character is taken from the first charset that has it.
+///
+/// This allows to keep a small charset, e.g. [`ASCII`](crate::ASCII), and add supplementary
+/// charsets for the characters that it doesn't have. Characters that are missing from all
+/// the charsets are substituted with '?' from the first charset.
+///
+/// Kerning, side bearings and ligatures only apply between glyphs of the same charset,
+/// glyphs from different charsets are separated by the default side bearings.
+/// All the glyphs are aligned on the baseline of the first charset,
+/// that also defines the line height.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FallbackChain<'a> {
+    charsets: &'a [Charset<'a>],
+}
+
+/// A glyph from one of the charsets in a fallback chain.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Glyph<'a> {
+    pub(crate) charset: &'a Charset<'a>,
+    pub(crate) index: GlyphIndex,
+    /// Vertical offset from the top of the line,
+    /// that aligns the glyph on the baseline of the first charset.
+    pub(crate) top: i32,
+}
+
+impl PartialEq for Glyph<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        core::ptr::eq(self.charset, other.charset) && self.index == other.index
+    }
+}
+
+impl Glyph<'_> {
+    /// Returns the area of the glyph in the font image.
+    pub(crate) fn area(&self) -> Rectangle {
+        self.charset.glyph_area(self.index)
+    }
+
+    /// Returns the width of the glyph.
+    pub(crate) fn width(&self) -> i32 {
+        self.charset.glyph_width(self.index)
+    }
+
+    /// Returns true if the glyph has a pixel set at the given point,
+    /// relative to the left edge of the glyph and the top of the line.
+    pub(crate) fn is_ink(&self, point: Point) -> bool {
+        self.charset
+            .is_ink(self.index, point - Point::new(0, self.top))
+    }
+}
+
+impl<'a> FallbackChain<'a> {
+    /// Creates a new fallback chain.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no charsets.
+    pub const fn new(charsets: &'a [Charset<'a>]) -> Self {
+        assert!(!charsets.is_empty(), "fallback chain must not be empty");
+        Self { charsets }
+    }
+
+    /// Returns the charsets in the order they are looked up.
+    pub fn charsets(&self) -> &'a [Charset<'a>] {
+        self.charsets
+    }
+
+    /// Returns the first charset, that defines the line metrics.
+    pub(crate) fn primary(&self) -> &'a Charset<'a> {
+        &self.charsets[0]
+    }
+
+    /// Returns an iterator over the glyphs for the characters in a text,
+    /// together with the byte offsets of their first characters.
+    /// Performs ligature substitution, if the ligatures are enabled.
+    pub(crate) fn glyphs<'t>(
+        &self,
+        text: &'t str,
+        ligatures: bool,
+    ) -> impl Iterator<Item = (usize, Glyph<'a>)> + 't
+    where
+        'a: 't,
+    {
+        let chain = *self;
+        let mut byte_offset = 0;
+        core::iter::from_fn(move || {
+            let (glyph, len) = chain.glyph_at(&text[byte_offset..], ligatures)?;
+            let start = byte_offset;
+            byte_offset += len;
+            Some((start, glyph))
+        })
+    }
+
+    /// Returns true if the text is the beginning of a longer ligature in any of the charsets.
+    pub(crate) fn is_ligature_prefix(&self, text: &str) -> bool {
+        self.charsets
+            .iter()
+            .any(|charset| charset.ligatures.is_prefix(text))
+    }
+
+    /// Returns the glyph for the start of a text,
+    /// together with the number of bytes that the glyph covers.
+    pub(crate) fn glyph_at(&self, text: &str, ligatures: bool) -> Option<(Glyph<'a>, usize)> {
+        let char = text.chars().next()?;
+        // The missing characters are replaced with the replacement glyph of the primary charset.
+        let (charset, (index, len)) = self
+            .charsets
+            .iter()
+            .find_map(|charset| Some((charset, charset.glyph_at(text, ligatures)?)))
+            .unwrap_or_else(|| {
+                let primary = self.primary();
+                (primary, (primary.glyph_index(char), char.len_utf8()))
+            });
+        let top = self.primary().baseline as i32 - charset.baseline as i32;
+        Some((
+            Glyph {
+                charset,
+                index,
+                top,
+            },
+            len,
+        ))
+    }
+
+    /// Returns the spacing between two glyphs, or between the start of the text and a glyph.
+    /// Takes into account the side bearings and kerning, if it is enabled,
+    /// of glyphs from the same charset.
+    pub(crate) fn spacing(
+        &self,
+        prev_glyph: Option<Glyph<'a>>,
+        next_glyph: Glyph<'a>,
+        kerning: bool,
+    ) -> i32 {
+        match prev_glyph {
+            Some(prev) if !core::ptr::eq(prev.charset, next_glyph.charset) => {
+                prev.charset.side_bearings.default_right()
+                    + next_glyph.charset.side_bearings.default_left()
+            }
+            prev => {
+                next_glyph
+                    .charset
+                    .spacing(prev.map(|prev| prev.index), next_glyph.index, kerning)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generated::ASCII;
+    use embedded_graphics::mono_font::mapping::StrGlyphMapping;
+
+    /// The ASCII charset without the lowercase letters and the ligatures.
+    const NO_LOWERCASE: Charset<'static> = Charset {
+        glyph_mapping: StrGlyphMapping::new("\0\u{20}\u{60}", 31),
+        ligatures: crate::ligatures::Ligatures::new("", 95),
+        ..ASCII
+    };
+
+    #[test]
+    fn test_substitute_unknown_glyphs_from_the_first_charset() {
+        let charsets = [NO_LOWERCASE, ASCII];
+        let chain = FallbackChain::new(&charsets);
+        let mut glyphs = chain.glyphs("熊", true).map(|(_, glyph)| glyph);
+        let glyph = glyphs.next().unwrap();
+        assert!(core::ptr::eq(glyph.charset, &charsets[0]));
+        assert_eq!(glyph.index, NO_LOWERCASE.glyph_index('?'));
+        assert_eq!(glyphs.next(), None);
+    }
+
+    #[test]
+    fn test_glyphs_from_the_first_charset_that_has_them() {
+        let charsets = [NO_LOWERCASE, ASCII];
+        let chain = FallbackChain::new(&charsets);
+        let mut glyphs = chain.glyphs("Tfi", true).map(|(_, glyph)| glyph);
+        let t = glyphs.next().unwrap();
+        assert!(core::ptr::eq(t.charset, &charsets[0]));
+        assert_eq!(t.index, ASCII.glyph_index('T'));
+        // Ligatures are substituted within the fallback charset
+        let fi = glyphs.next().unwrap();
+        assert!(core::ptr::eq(fi.charset, &charsets[1]));
+        assert_eq!(fi.index, GlyphIndex(ASCII.ligatures.offset + 2));
+        assert_eq!(glyphs.next(), None);
+    }
+
+    #[test]
+    fn test_spacing() {
+        let charsets = [NO_LOWERCASE, ASCII];
+        let chain = FallbackChain::new(&charsets);
+        let mut glyphs = chain.glyphs("/fo", true).map(|(_, glyph)| glyph);
+        let (slash, f, o) = (
+            glyphs.next().unwrap(),
+            glyphs.next().unwrap(),
+            glyphs.next().unwrap(),
+        );
+        // Kerning between glyphs of the same charset
+        assert_eq!(chain.spacing(Some(f), o, true), 0);
+        // No kerning between glyphs of different charsets
+        assert_eq!(chain.spacing(Some(slash), f, true), 1);
+    }
+}
@@ -0,0 +1,390 @@
+//! The binary format of a charset, that can be loaded at runtime.
+//!
+//! All the numbers are little-endian. The file starts with a header:
+//!
+//! | Offset | Type    | Description                                  |
+//! |--------|---------|----------------------------------------------|
+//! | 0      | [u8; 4] | Magic bytes `MOGF`                           |
+//! | 4      | u8      | Format version, currently 1                  |
+//! | 5      | u8      | Line height                                  |
+//! | 6      | u8      | Baseline                                     |
+//! | 7      | u8      | Underline offset below the baseline          |
+//! | 8      | u8      | x-height                                     |
+//! | 9      | i8      | Default left side bearing                    |
+//! | 10     | i8      | Default right side bearing                   |
+//! | 11     | u8      | Glyph format, 0 for compact, 1 for wide      |
+//! | 12     | u16     | Atlas width in pixels                        |
+//! | 14     | u16     | Index of the substitute glyph                |
+//! | 16     | u16     | Index of the first ligature glyph            |
+//!
+//! It is followed by the sections, each prefixed with its length in bytes as u32:
+//!
+//! 1. Glyph mapping, a UTF-8 string in the format of `StrGlyphMapping`.
+//! 2. Ligatures, a UTF-8 string of `\0` separated ligatures.
+//! 3. Glyph data, in the compact format 5 bytes per glyph: atlas x and y as u8,
+//!    height and width packed in 4 bits each, left and right kerning classes;
+//!    in the wide format 8 bytes per glyph: atlas x and y as u16, width and height as u8,
+//!    left and right kerning classes.
+//! 4. Side bearings, 4 bytes per glyph, sorted by the glyph index:
+//!    glyph index as u16, left and right bearings as i8.
+//! 5. Kerning pairs, 3 bytes per pair, sorted by the classes:
+//!    left and right kerning classes as u8, kerning as i8.
+//! 6. Kerning overrides, 5 bytes per override, sorted by the glyph indices:
+//!    left and right glyph indices as u16, kerning as i8.
+//! 7. Atlas, a 1 bit per pixel image with rows padded to whole bytes.
+use crate::{
+    charset::{Charset, GlyphFormat, GlyphIndex},
+    kerning::Kerning,
+    ligatures::Ligatures,
+    side_bearings::SideBearings,
+};
+use core::fmt;
+use embedded_graphics::{
+    geometry::{OriginDimensions, Point},
+    image::ImageRaw,
+    mono_font::mapping::StrGlyphMapping,
+    primitives::Rectangle,
+};
+
+/// The magic bytes at the start of a font file.
+pub(crate) const MAGIC: [u8; 4] = *b"MOGF";
+
+/// The current version of the format.
+pub(crate) const VERSION: u8 = 1;
+
+/// An error that occurs when loading a charset from bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FontError {
+    /// The data doesn't start with the magic bytes.
+    InvalidMagic,
+    /// The format version is not supported.
+    UnsupportedVersion(u8),
+    /// The data ends in the middle of the header or a section.
+    UnexpectedEnd,
+    /// There is data after the last section.
+    TrailingData,
+    /// The glyph mapping or the ligatures are not valid UTF-8.
+    InvalidUtf8,
+    /// The line height, baseline, underline offset and x-height don't fit together.
+    InvalidMetrics,
+    /// The atlas is empty or its size doesn't match the atlas width.
+    InvalidAtlas,
+    /// The glyph format is unknown, the glyph data has a partial record,
+    /// or a glyph outside of the atlas.
+    InvalidGlyphData,
+    /// A table has a partial record, or its records are not sorted.
+    InvalidTable,
+    /// The glyph mapping or the ligatures refer to glyphs that don't exist.
+    GlyphIndexOutOfRange,
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FontError::InvalidMagic => f.write_str("not a MogeeFont file"),
+            FontError::UnsupportedVersion(version) => {
+                write!(f, "unsupported MogeeFont format version {}", version)
+            }
+            FontError::UnexpectedEnd => f.write_str("unexpected end of data"),
+            FontError::TrailingData => f.write_str("unexpected data after the last section"),
+            FontError::InvalidUtf8 => f.write_str("invalid UTF-8 in glyph mapping or ligatures"),
+            FontError::InvalidMetrics => f.write_str("invalid line metrics"),
+            FontError::InvalidAtlas => f.write_str("invalid atlas size"),
+            FontError::InvalidGlyphData => f.write_str("invalid glyph data"),
+            FontError::InvalidTable => f.write_str("invalid side bearings or kerning table"),
+            FontError::GlyphIndexOutOfRange => f.write_str("glyph index out of range"),
+        }
+    }
+}
+
+/// Reads the values from the start of the data.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], FontError> {
+        if len > self.data.len() {
+            return Err(FontError::UnexpectedEnd);
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, FontError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn i8(&mut self) -> Result<i8, FontError> {
+        Ok(self.u8()? as i8)
+    }
+
+    fn u16(&mut self) -> Result<u16, FontError> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, FontError> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn section(&mut self) -> Result<&'a [u8], FontError> {
+        let len = self.u32()? as usize;
+        self.bytes(len)
+    }
+
+    fn str_section(&mut self) -> Result<&'a str, FontError> {
+        core::str::from_utf8(self.section()?).map_err(|_| FontError::InvalidUtf8)
+    }
+}
+
+/// Parses and validates a charset in the binary format.
+pub(crate) fn parse(data: &[u8]) -> Result<Charset<'_>, FontError> {
+    let mut reader = Reader { data };
+
+    if reader.bytes(MAGIC.len())? != MAGIC {
+        return Err(FontError::InvalidMagic);
+    }
+    let version = reader.u8()?;
+    if version != VERSION {
+        return Err(FontError::UnsupportedVersion(version));
+    }
+
+    let line_height = reader.u8()? as u32;
+    let baseline = reader.u8()? as u32;
+    let underline_offset = reader.u8()? as u32;
+    let x_height = reader.u8()? as u32;
+    let default_bearings = (reader.i8()? as i32, reader.i8()? as i32);
+    let glyph_format = match reader.u8()? {
+        0 => GlyphFormat::Compact,
+        1 => GlyphFormat::Wide,
+        _ => return Err(FontError::InvalidGlyphData),
+    };
+    let atlas_width = reader.u16()? as u32;
+    let substitute_index = reader.u16()? as usize;
+    let ligature_offset = reader.u16()? as usize;
+
+    let glyph_mapping = reader.str_section()?;
+    let ligatures = reader.str_section()?;
+    let glyph_data = reader.section()?;
+    let side_bearings = reader.section()?;
+    let kerning_pairs = reader.section()?;
+    let kerning_overrides = reader.section()?;
+    let atlas = reader.section()?;
+    if !reader.data.is_empty() {
+        return Err(FontError::TrailingData);
+    }
+
+    let bytes_per_row = atlas_width.div_ceil(8) as usize;
+    if atlas.is_empty() || bytes_per_row == 0 || atlas.len() % bytes_per_row != 0 {
+        return Err(FontError::InvalidAtlas);
+    }
+    if glyph_data.len() % glyph_format.record_size() != 0 {
+        return Err(FontError::InvalidGlyphData);
+    }
+    if substitute_index >= glyph_data.len() / glyph_format.record_size() {
+        return Err(FontError::GlyphIndexOutOfRange);
+    }
+
+    let charset = Charset {
+        image: ImageRaw::new(atlas, atlas_width),
+        glyph_mapping: StrGlyphMapping::new(glyph_mapping, substitute_index),
+        glyph_data,
+        glyph_format,
+        ligatures: Ligatures::new(ligatures, ligature_offset),
+        side_bearings: SideBearings::from_bytes(side_bearings, default_bearings),
+        kerning: Kerning::from_bytes(kerning_pairs, kerning_overrides),
+        line_height,
+        baseline,
+        underline_offset,
+        x_height,
+    };
+    validate(&charset)?;
+    Ok(charset)
+}
+
+/// Checks that the charset can be used without panics.
+fn validate(charset: &Charset<'_>) -> Result<(), FontError> {
+    if charset.baseline + charset.underline_offset >= charset.line_height
+        || charset.x_height.div_ceil(2) > charset.baseline
+    {
+        return Err(FontError::InvalidMetrics);
+    }
+
+    let atlas = Rectangle::new(Point::zero(), charset.image.size());
+    for index in 0..charset.glyph_count() {
+        let area = charset.glyph_area(GlyphIndex(index));
+        if let Some(bottom_right) = area.bottom_right() {
+            if !atlas.contains(area.top_left) || !atlas.contains(bottom_right) {
+                return Err(FontError::InvalidGlyphData);
+            }
+        }
+    }
+
+    if charset.glyph_mapping.chars().count() > charset.glyph_count()
+        || charset.ligatures.offset + charset.ligatures.len() > charset.glyph_count()
+    {
+        return Err(FontError::GlyphIndexOutOfRange);
+    }
+
+    let side_bearings = charset.side_bearings.bearings;
+    let pairs = charset.kerning.pairs;
+    let overrides = charset.kerning.overrides;
+    if !side_bearings.has_whole_records()
+        || !side_bearings.is_sorted_by_key(|bearing| bearing.0)
+        || !pairs.has_whole_records()
+        || !pairs.is_sorted_by_key(|pair| (pair.0, pair.1))
+        || !overrides.has_whole_records()
+        || !overrides.is_sorted_by_key(|kerning| (kerning.0, kerning.1))
+    {
+        return Err(FontError::InvalidTable);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::{
+        fallback_chain::FallbackChain,
+        generated::ASCII,
+        table::{Record, Table},
+    };
+    use std::vec::Vec;
+
+    fn push_section(file: &mut Vec<u8>, section: &[u8]) {
+        file.extend_from_slice(&(section.len() as u32).to_le_bytes());
+        file.extend_from_slice(section);
+    }
+
+    fn encode_table<T: Record>(table: Table<T>, encode: impl Fn(T) -> Vec<u8>) -> Vec<u8> {
+        (0..table.len())
+            .flat_map(|i| encode(table.get(i)))
+            .collect()
+    }
+
+    /// Encodes the ASCII charset the same way as generate-font does.
+    fn ascii_file(glyph_format: GlyphFormat) -> Vec<u8> {
+        let glyph_data: Vec<u8> = match glyph_format {
+            GlyphFormat::Compact => ASCII.glyph_data.to_vec(),
+            GlyphFormat::Wide => (0..ASCII.glyph_count())
+                .flat_map(|index| {
+                    let area = ASCII.glyph_area(GlyphIndex(index));
+                    let record = &ASCII.glyph_data[index * 5..index * 5 + 5];
+                    let mut wide = Vec::new();
+                    wide.extend_from_slice(&(area.top_left.x as u16).to_le_bytes());
+                    wide.extend_from_slice(&(area.top_left.y as u16).to_le_bytes());
+                    wide.extend_from_slice(&[area.size.width as u8, area.size.height as u8]);
+                    wide.extend_from_slice(&record[3..]);
+                    wide
+                })
+                .collect(),
+        };
+        let mut file = Vec::new();
+        file.extend_from_slice(&MAGIC);
+        file.extend_from_slice(&[VERSION, 11, 8, 1, 5, 0, 1, glyph_format as u8]);
+        file.extend_from_slice(&128u16.to_le_bytes());
+        file.extend_from_slice(&31u16.to_le_bytes());
+        file.extend_from_slice(&95u16.to_le_bytes());
+        push_section(&mut file, "\0\u{20}\u{7e}".as_bytes());
+        push_section(&mut file, "\0ffi\0ff\0fi\0fj\0gj\0jj\0ss\0yj".as_bytes());
+        push_section(&mut file, &glyph_data);
+        push_section(
+            &mut file,
+            &encode_table(ASCII.side_bearings.bearings, |(glyph, left, right)| {
+                let [glyph_low, glyph_high] = (glyph as u16).to_le_bytes();
+                std::vec![glyph_low, glyph_high, left as u8, right as u8]
+            }),
+        );
+        push_section(
+            &mut file,
+            &encode_table(ASCII.kerning.pairs, |(left, right, kerning)| {
+                std::vec![left, right, kerning as u8]
+            }),
+        );
+        push_section(
+            &mut file,
+            &encode_table(ASCII.kerning.overrides, |(left, right, kerning)| {
+                let mut record = Vec::new();
+                record.extend_from_slice(&(left as u16).to_le_bytes());
+                record.extend_from_slice(&(right as u16).to_le_bytes());
+                record.push(kerning as u8);
+                record
+            }),
+        );
+        push_section(&mut file, include_bytes!("ascii_font.raw"));
+        file
+    }
+
+    #[test]
+    fn test_parse() {
+        for glyph_format in [GlyphFormat::Compact, GlyphFormat::Wide] {
+            let file = ascii_file(glyph_format);
+            assert_parsed_ascii(parse(&file).unwrap());
+        }
+    }
+
+    fn assert_parsed_ascii(charset: Charset) {
+        assert_eq!(charset.line_height(), ASCII.line_height());
+        assert_eq!(charset.baseline(), ASCII.baseline());
+        assert_eq!(charset.glyph_count(), ASCII.glyph_count());
+        assert_eq!(charset.image, ASCII.image);
+        assert_eq!(charset.glyph_mapping, ASCII.glyph_mapping);
+        assert_eq!(charset.ligatures, ASCII.ligatures);
+
+        // The glyphs, side bearings and kerning are the same
+        let text = "Tf/o, jyj ffi!";
+        let charsets = [charset];
+        let parsed = FallbackChain::new(&charsets);
+        let ascii = FallbackChain::new(core::slice::from_ref(&ASCII));
+        let mut prev = None;
+        for ((_, parsed_glyph), (_, ascii_glyph)) in
+            parsed.glyphs(text, true).zip(ascii.glyphs(text, true))
+        {
+            assert_eq!(parsed_glyph.index, ascii_glyph.index);
+            assert_eq!(
+                parsed.spacing(prev.map(|(p, _)| p), parsed_glyph, true),
+                ascii.spacing(prev.map(|(_, a)| a), ascii_glyph, true)
+            );
+            assert_eq!(parsed_glyph.area(), ascii_glyph.area());
+            prev = Some((parsed_glyph, ascii_glyph));
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let file = ascii_file(GlyphFormat::Compact);
+        assert_eq!(parse(b"MOGE"), Err(FontError::InvalidMagic));
+        assert_eq!(parse(&file[..20]), Err(FontError::UnexpectedEnd));
+
+        let mut unsupported = file.clone();
+        unsupported[4] = 99;
+        assert_eq!(parse(&unsupported), Err(FontError::UnsupportedVersion(99)));
+
+        let mut trailing = file.clone();
+        trailing.push(0);
+        assert_eq!(parse(&trailing), Err(FontError::TrailingData));
+
+        let mut baseline = file.clone();
+        baseline[6] = 11;
+        assert_eq!(parse(&baseline), Err(FontError::InvalidMetrics));
+
+        let mut substitute = file.clone();
+        substitute[14] = 200;
+        assert_eq!(parse(&substitute), Err(FontError::GlyphIndexOutOfRange));
+
+        let mut glyph_format = file.clone();
+        glyph_format[11] = 2;
+        assert_eq!(parse(&glyph_format), Err(FontError::InvalidGlyphData));
+
+        let mut atlas_width = file.clone();
+        atlas_width[12] = 0;
+        assert_eq!(parse(&atlas_width), Err(FontError::InvalidAtlas));
+    }
+}
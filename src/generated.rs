@@ -1,5 +1,5 @@
 use crate::{
-    charset::Charset, kerning::Kerning, ligatures::Ligatures, side_bearings::SideBearings
+    charset::{Charset, GlyphFormat}, kerning::Kerning, ligatures::Ligatures, side_bearings::SideBearings
 };
 use embedded_graphics::{image::ImageRaw, mono_font::mapping::StrGlyphMapping};
 
@@ -13,6 +13,7 @@ pub const ASCII: Charset = Charset {
         31,
     ),
     glyph_data: include_bytes!("ascii_glyph_data.raw"),
+    glyph_format: GlyphFormat::Compact,
     side_bearings: SideBearings::new(
         &[(0, 0, 0), (12, 0, 1), (27, 0, 1), (74, -2, 1), (100, -2, 1)],
         (0, 1),
@@ -27,4 +28,6 @@ pub const ASCII: Charset = Charset {
     ),
     line_height: 11,
     baseline: 8,
+    underline_offset: 1,
+    x_height: 5,
 };
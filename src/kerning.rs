@@ -1,27 +1,37 @@
-use crate::charset::GlyphIndex;
+use crate::{charset::GlyphIndex, table::Table};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Kerning<'a> {
-    pairs: &'a [(u8, u8, i32)],
-    overrides: &'a [(usize, usize, i32)],
+    pub(crate) pairs: Table<'a, (u8, u8, i32)>,
+    pub(crate) overrides: Table<'a, (usize, usize, i32)>,
 }
 
 impl<'a> Kerning<'a> {
     pub const fn new(pairs: &'a [(u8, u8, i32)], overrides: &'a [(usize, usize, i32)]) -> Self {
-        Self { pairs, overrides }
+        Self {
+            pairs: Table::Slice(pairs),
+            overrides: Table::Slice(overrides),
+        }
+    }
+
+    pub const fn from_bytes(pairs: &'a [u8], overrides: &'a [u8]) -> Self {
+        Self {
+            pairs: Table::Bytes(pairs),
+            overrides: Table::Bytes(overrides),
+        }
     }
 
     pub fn kerning_override(&self, left: GlyphIndex, right: GlyphIndex) -> Option<i32> {
         self.overrides
             .binary_search_by_key(&(left.0, right.0), |(l, r, _)| (*l, *r))
-            .map(|i| self.overrides[i].2)
+            .map(|i| self.overrides.get(i).2)
             .ok()
     }
 
     pub fn kerning(&self, left_class: u8, right_class: u8) -> Option<i32> {
         self.pairs
             .binary_search_by_key(&(left_class, right_class), |(l, r, _)| (*l, *r))
-            .map(|i| self.pairs[i].2)
+            .map(|i| self.pairs.get(i).2)
             .ok()
     }
 }
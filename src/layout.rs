@@ -0,0 +1,55 @@
+use crate::charset::Charset;
+use core::ops::Range;
+use embedded_graphics::{geometry::Point, primitives::Rectangle};
+
+/// A glyph in a line of text, positioned by [`MogeeTextStyle::layout`](crate::MogeeTextStyle::layout).
+///
+/// The positions take into account the side bearings, kerning, ligatures
+/// and all the options of the text style, so custom renderers can draw the glyphs
+/// from the [charset image](Charset::image) exactly where `draw_string` draws them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionedGlyph {
+    pub(crate) charset: &'static Charset<'static>,
+    pub(crate) index: usize,
+    pub(crate) bytes: Range<usize>,
+    pub(crate) position: Point,
+    pub(crate) advance: i32,
+    pub(crate) area: Rectangle,
+}
+
+impl PositionedGlyph {
+    /// Returns the charset of the glyph.
+    pub fn charset(&self) -> &'static Charset<'static> {
+        self.charset
+    }
+
+    /// Returns the index of the glyph in the charset,
+    /// the ligatures follow the glyphs for the single characters.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the byte range of the characters in the text, that the glyph is drawn for.
+    ///
+    /// A ligature covers more than one character.
+    pub fn bytes(&self) -> Range<usize> {
+        self.bytes.clone()
+    }
+
+    /// Returns the position of the top left corner of the glyph in pixels,
+    /// relative to the start of the line at the top of the line.
+    pub fn position(&self) -> Point {
+        self.position
+    }
+
+    /// Returns the distance in pixels from the left edge of the glyph to the end of its advance,
+    /// where the side bearings and kerning of the next glyph are applied.
+    pub fn advance(&self) -> i32 {
+        self.advance
+    }
+
+    /// Returns the area of the glyph in the charset image.
+    pub fn area(&self) -> Rectangle {
+        self.area
+    }
+}
@@ -55,6 +55,17 @@
 //! | `ASCII` | ![ASCII](data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAQAAAABeCAAAAADp6pWJAAAGzElEQVR4Ae3gAZAkSZIkSRKLqpm7R0REZmZmVlVVVVV3d3d3d/fMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMdHd3d3dXV1VVVVVmZkZGRIS7m5kKz0xmV3d1d3dPz8zMzMxMYuUBjDDiuRnxLzHi+THiRWfEfykq/79ReSYjnj8DRoARAEYAGBDPyQgjAIwAMOJFZwDEFUaAARBgrhAABoS5QgAYEOYKYQAEGADxAFT+f6PyAAIEgBFGgBFgwAgj7mcEGAEgAIwAYQQIA0a8aIwAEGAEmPsJMEIAGAAjDIC4nxEAAsAACDACBBjxLFT+f6PyfBgwYAQACBBGgBHPJl4wI4wwAsCIfw0jDIB4NgMCjLifAQFG/KtQ+f+NyvMhjDAChAEBIMCIK4QBAUYAGAEAwghxhXhRGPGcxAMZASDAiGcTAEaAAXGFESAMCBAGAIwAIyr/v1F5PgwYMAIEGAFgBBgBRoARL4y4wgAYEFeYK8SLwojnZp6XACPACAAjwAgQYEAACKj8/0blmcSzCSOMACPuZwQYYcQVwoh/mQAjnk08kBEvmBFXGHGFADBgxIvKiAeg8v8blWcy4tkEiCsMiGcTRoAwAMIIAGEEgBEvjBHPZsSzCXOFATACDIAQ5rkJAwDCPDdhrjAgAIwAIyr/vyH+RUa8qIwAMOKFMeK/nxGV/98Q/8GMMOKFMeK/nxFQ+f8N8f8blf/fqPz/RuX/Nyr/v1H5/43Ki8CAeH4MCDAAAsAACAMgwAAIAyDAAAgjwAgDIADACCPACCMAjAAwwggwAgwIMAACwIAAAyAADIAw9xOV/9+o/IuMACMAzBUCjAAjQIARRoABEGAECAAQAEaAeSBhxL+FEWAECDDCCDACBBhhBBgQYARA5f83KgAYEM9mQFwhns2IK4x4wcS/TPzLjHggI8wVRvzriedA5f83KhgQAOYKIcCAADAgwAgwIJ6bAfGCGBBgQAAYEGBeMCOekzDCAAjzojAgQBgQz4HK/29UI+4nHkiAEQDifkaY+5n7CTDi+RMAIO4nDIAA8/wJI14wYZ6TAfGchLlCgBEPQOX/N6owIADMFQLAgAAQL4gA87yM+Lcx4tmEEQ8kXhjx/AgjjHgeVP5/o4IAA0Lcz4C4nxH3EwaMeE4GBAgDAsCAADAg7icMiAcyYIQwIJ6TAfH8CQPigYQB8ZwMiOdA5f83xL+KEf97GPEvoPL/G+JfyQCI/yOo/P+G+P+Nyv9vVP5/o/L/G5X/QEa8cEb8j0Ll/zeqEWCEEWCEEWAEGGEEgBFGABhhrhAGxLMZEGDEAwkj7mfEczPigQyI+xkBYASAEUYAGPGvRuX/Nyr/DgLAAAgQRgAIA0YY8UDiX8OI50cAgPh3ovL/G5UXgTDCCBBGGHGFES+IAPG8DAgAI8CAADAAYEAYMALAgAAjwAgAIwCMADAAIMCAAAMCwIAwIMCAqPz/RuVFIox4XuY5CSP+JQKMAGEECCOMMGCEAWEEgBEGQBjxggkjjABhBAgjjDAAwggQpvL/G5UXiRFGAAgj7if+9czzEg9k/jWM+Dei8v8bledgxBUGwAgwAoQRD2TEcxNG/EuEeU5GPJt4TsKIF0QY8W8hKv+/UYUBAcIIAGEEGPHCGQDxryMMgDAAwgCAESAMiGcz4t9KmCuMAGGehcr/b4j/EEY8JyP+/Yy4woB4bka8qIy4nxGXUfn/jcp/ECOezfxHE+Y/AZX/36j8hxDPSTyQuUL8WxkQz8mA+Hei8v8b4v83Kv+/Ufn/jcr/b1T+gxhhhBHPywgDAsAIAwLAgAAwIO5nBIARRjx/Rvw7UPn/DfFMRvzbGQFGgBHPyQgjrjDCiCuMuMKI/3JU/n+jggEBBoQRRhhhhBFGGGGEEUYYYQDEC2fAgAFhwIARYMCAMGBAgAFhBIARRoABYUAYYYQR/w5U/n+jGnGF+NcSYF4QI4wQRhhhQBhhBIAwwoAwwoAR5nkZAUb8B6Ly/xtVGHGFES+MMOKBjAAjnk0YYYQR/3GEEcKI/zBU/n+jgjDiRWGEEfczAkAYcT8jQID4txBGPD/CCGHEfxAq/79RDQgQ5gpzhREARhjxbMIAgAHxH8uI58eAMCBAmP8AVP5/o4r7CQBxhbhCAAgAAQJAGBD/OuL5Ew8kDIjnJgDE/YT5d6Py/xuV/xDCCAAj/m3E/YT5L0Ll/zcq/wYGxHMSV4h/KwMCDAgjwIAw4rkZEEb8u1D5/41/BGGV8NSunz09AAAAAElFTkSuQmCC) |
 //END-SPECIMEN
 //!
+//! The `ASCII` charset is used by default. Other charsets are passed to
+//! [`MogeeTextStyle::with_charset`]:
+//!
+//! ```rust
+//! use embedded_mogeefont::{MogeeTextStyle, ASCII};
+//! use embedded_graphics::{pixelcolor::BinaryColor, text::renderer::TextRenderer};
+//!
+//! let character_style = MogeeTextStyle::with_charset(BinaryColor::On, &ASCII);
+//! assert_eq!(character_style.line_height(), 11);
+//! ```
+//!
 #![no_std]
 #![deny(missing_docs)]
 #![deny(missing_debug_implementations)]
@@ -67,12 +78,37 @@
 #![deny(unused_qualifications)]
 #![deny(rustdoc::broken_intra_doc_links)]
 #![deny(rustdoc::private_intra_doc_links)]
+mod ansi;
 mod charset;
+mod console;
 mod draw_target;
+mod fallback_chain;
+mod font_file;
 mod generated;
 mod kerning;
+mod layout;
 mod ligatures;
+mod markup;
+mod marquee;
+mod paragraph;
+mod rotation;
+mod run_style;
 mod side_bearings;
+mod table;
 mod text_style;
+mod writer;
 
-pub use text_style::TextStyle as MogeeTextStyle;
+pub use ansi::{Ansi, AnsiStyle};
+pub use charset::Charset;
+pub use console::Console;
+pub use fallback_chain::FallbackChain;
+pub use font_file::FontError;
+pub use generated::ASCII;
+pub use layout::PositionedGlyph;
+pub use markup::{Markup, MarkupStyle};
+pub use marquee::Marquee;
+pub use paragraph::{Paragraph, ParagraphAlignment};
+pub use rotation::{RotatedDrawTarget, Rotation};
+pub use run_style::{RunStyle, Runs};
+pub use text_style::{Figures, SpanStyle, TextEffect, TextStyle as MogeeTextStyle, Truncation};
+pub use writer::TextWriter;
@@ -10,8 +10,13 @@ impl<'a> Ligatures<'a> {
         Self { data, offset }
     }
 
+    /// Returns the number of ligatures.
+    pub fn len(&self) -> usize {
+        self.data.split('\0').filter(|liga| !liga.is_empty()).count()
+    }
+
     /// Return the index of the ligature glyph
-    /// and the number of chars to skip
+    /// and the number of bytes to skip
     /// if the string starts with a ligature.
     pub fn substitute(&self, str: &str) -> Option<(usize, usize)> {
         let mut offset = self.offset;
@@ -26,6 +31,13 @@ impl<'a> Ligatures<'a> {
         }
         None
     }
+
+    /// Returns true if the string is the beginning of a longer ligature.
+    pub fn is_prefix(&self, str: &str) -> bool {
+        self.data
+            .split('\0')
+            .any(|liga| liga.len() > str.len() && liga.starts_with(str))
+    }
 }
 
 #[cfg(test)]
@@ -33,6 +45,7 @@ mod tests {
     use super::*;
 
     #[test]
+    #[allow(clippy::identity_op)]
     fn test_substitute() {
         let offset = 31;
         let mapping = Ligatures::new("\0\u{66}\u{66}\u{69}\0\u{66}\u{66}\0\u{66}\u{69}\0\u{66}\u{6a}\0\u{67}\u{6a}\0\u{6a}\u{6a}\0\u{73}\u{73}\0\u{79}\u{6a}", offset);
@@ -41,5 +54,10 @@ mod tests {
         assert_eq!(mapping.substitute("ff"), Some((offset + 1, 2)));
         assert_eq!(mapping.substitute("fi"), Some((offset + 2, 2)));
         assert_eq!(mapping.substitute("yj"), Some((offset + 7, 2)));
+        assert_eq!(mapping.len(), 8);
+        assert!(mapping.is_prefix("f"));
+        assert!(mapping.is_prefix("ff"));
+        assert!(!mapping.is_prefix("ffi"));
+        assert!(!mapping.is_prefix("a"));
     }
 }
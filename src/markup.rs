@@ -0,0 +1,277 @@
+use crate::{
+    run_style::{RunStyle, Runs},
+    text_style::{SpanStyle, TextStyle},
+};
+use embedded_graphics::{pixelcolor::PixelColor, text::DecorationColor};
+
+/// A parser of the inline markup, that splits a text into styled runs.
+///
+/// The supported tags are `{c:name}` for the text color from the palette,
+/// `{b}` for bold, `{u}` for underline and `{s}` for strikethrough,
+/// each closed with `{/c}`, `{/b}`, `{/u}` and `{/s}`, that restore the initial style.
+/// The unknown tags and the colors missing from the palette are a part of the text.
+#[derive(Clone, Debug)]
+pub struct Markup<'a, C> {
+    text: &'a str,
+    initial_style: SpanStyle<C>,
+    style: SpanStyle<C>,
+    palette: &'a [(&'a str, C)],
+}
+
+impl<'a, C> Markup<'a, C>
+where
+    C: Copy,
+{
+    /// Creates a new parser of the text with the initial style,
+    /// and the palette of the named colors.
+    pub fn new(text: &'a str, style: SpanStyle<C>, palette: &'a [(&'a str, C)]) -> Self {
+        Self {
+            text,
+            initial_style: style,
+            style,
+            palette,
+        }
+    }
+
+    /// Returns the length of the known tag at the start of the text,
+    /// together with the style after it.
+    fn tag(&self, text: &str) -> Option<(usize, SpanStyle<C>)> {
+        let tag = text.strip_prefix('{')?;
+        let tag = &tag[..tag.find('}')?];
+        let initial_style = self.initial_style;
+        let mut style = self.style;
+        match tag {
+            "b" => style.bold = true,
+            "/b" => style.bold = initial_style.bold,
+            "u" => style.underline_color = DecorationColor::TextColor,
+            "/u" => style.underline_color = initial_style.underline_color,
+            "s" => style.strikethrough_color = DecorationColor::TextColor,
+            "/s" => style.strikethrough_color = initial_style.strikethrough_color,
+            "/c" => style.text_color = initial_style.text_color,
+            _ => {
+                let name = tag.strip_prefix("c:")?;
+                let &(_, color) = self.palette.iter().find(|(n, _)| *n == name)?;
+                style.text_color = Some(color);
+            }
+        }
+        Some((tag.len() + 2, style))
+    }
+}
+
+impl<'a, C> Iterator for Markup<'a, C>
+where
+    C: Copy,
+{
+    type Item = (&'a str, SpanStyle<C>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((len, style)) = self.tag(self.text) {
+            self.text = &self.text[len..];
+            self.style = style;
+        }
+        if self.text.is_empty() {
+            return None;
+        }
+        // The run continues until the next known tag.
+        let end = self
+            .text
+            .match_indices('{')
+            .map(|(index, _)| index)
+            .find(|&index| index > 0 && self.tag(&self.text[index..]).is_some())
+            .unwrap_or(self.text.len());
+        let (run, rest) = self.text.split_at(end);
+        self.text = rest;
+        Some((run, self.style))
+    }
+}
+
+/// A text renderer for the text with the inline [`Markup`].
+///
+/// The markup is parsed separately in every string passed to the renderer,
+/// so the tags need to be closed within one line of a [`Text`](embedded_graphics::text::Text),
+/// and within one word of a `TextBox`. To keep the tags open across the words and the lines,
+/// wrap the whole text with [`Paragraph::draw_runs`](crate::Paragraph::draw_runs):
+///
+/// ```
+/// use embedded_graphics::{mock_display::MockDisplay, pixelcolor::Rgb888, prelude::*};
+/// use embedded_mogeefont::{MarkupStyle, MogeeTextStyle, Paragraph};
+///
+/// let style = MogeeTextStyle::new(Rgb888::WHITE);
+/// let markup_style = MarkupStyle::new(style, &[("red", Rgb888::RED)]);
+/// let paragraph = Paragraph::new(style, 30);
+/// let mut display = MockDisplay::new();
+/// let text = "{c:red}Disk full,{/c} {u}free some space{/u}";
+/// paragraph
+///     .draw_runs(markup_style.markup(text), Point::zero(), &mut display)
+///     .unwrap();
+/// ```
+pub type MarkupStyle<'a, C> = RunStyle<C, &'a [(&'a str, C)]>;
+
+impl<'a, C> MarkupStyle<'a, C>
+where
+    C: PixelColor,
+{
+    /// Creates a new markup renderer with the text style,
+    /// and the palette of the named colors for the `{c:name}` tags.
+    pub fn new(style: TextStyle<C>, palette: &'a [(&'a str, C)]) -> Self {
+        Self::with_runs(style, palette)
+    }
+
+    /// Returns the parser of the markup in the text.
+    pub fn markup<'t>(&'t self, text: &'t str) -> Markup<'t, C> {
+        self.runs(text)
+    }
+}
+
+impl<'a, C> Runs<C> for &'a [(&'a str, C)]
+where
+    C: Copy,
+{
+    type Iter<'t>
+        = Markup<'t, C>
+    where
+        Self: 't;
+
+    fn runs<'t>(&'t self, text: &'t str, style: SpanStyle<C>) -> Self::Iter<'t> {
+        Markup::new(text, style, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::Paragraph;
+    use embedded_graphics::{
+        geometry::{Point, Size},
+        mock_display::MockDisplay,
+        pixelcolor::{Rgb888, RgbColor},
+        primitives::Rectangle,
+        text::{
+            renderer::{CharacterStyle, TextRenderer},
+            Baseline,
+        },
+        Drawable,
+    };
+    use embedded_text::TextBox;
+    use std::vec::Vec;
+
+    const PALETTE: [(&str, Rgb888); 2] = [("red", Rgb888::RED), ("green", Rgb888::GREEN)];
+
+    #[test]
+    fn test_parse_markup() {
+        let style = SpanStyle::new(Rgb888::WHITE);
+        let runs: Vec<_> = Markup::new("{c:red}Error{/c}: {u}retry{/u}", style, &PALETTE).collect();
+        assert_eq!(runs.len(), 3);
+        assert_eq!(runs[0], ("Error", SpanStyle::new(Rgb888::RED)));
+        assert_eq!(runs[1], (": ", style));
+        let mut underlined = style;
+        underlined.underline_color = DecorationColor::TextColor;
+        assert_eq!(runs[2], ("retry", underlined));
+
+        let runs: Vec<_> = Markup::new("{b}{c:green}ok", style, &PALETTE).collect();
+        let mut bold = SpanStyle::new(Rgb888::GREEN);
+        bold.bold = true;
+        assert_eq!(runs, [("ok", bold)]);
+    }
+
+    #[test]
+    fn test_unknown_tags_are_literal() {
+        let style = SpanStyle::new(Rgb888::WHITE);
+        let text = "{i}a{c:blue}b{/c}{x";
+        let runs: Vec<_> = Markup::new(text, style, &PALETTE).collect();
+        assert_eq!(runs, [("{i}a{c:blue}b", style), ("{x", style)]);
+
+        let runs: Vec<_> = Markup::new("é{", style, &PALETTE).collect();
+        assert_eq!(runs, [("é{", style)]);
+    }
+
+    #[test]
+    fn test_measure_ignores_markup() {
+        let style = TextStyle::new(Rgb888::WHITE);
+        let markup_style = MarkupStyle::new(style, &PALETTE);
+        assert_eq!(
+            markup_style.measure_string("{c:red}H{/c}i{u}!{/u}", Point::zero(), Baseline::Top),
+            style.measure_string("Hi!", Point::zero(), Baseline::Top)
+        );
+        let mut bold = style;
+        bold.set_bold(true);
+        assert_eq!(
+            markup_style.measure_string("{b}Hi!{/b}", Point::zero(), Baseline::Top),
+            bold.measure_string("Hi!", Point::zero(), Baseline::Top)
+        );
+        assert_eq!(
+            markup_style.measure_string("{q}", Point::zero(), Baseline::Top),
+            style.measure_string("{q}", Point::zero(), Baseline::Top)
+        );
+    }
+
+    #[test]
+    fn test_draw_markup() {
+        let style = TextStyle::new(Rgb888::WHITE);
+        let markup_style = MarkupStyle::new(style, &PALETTE);
+        let mut display = MockDisplay::new();
+        markup_style
+            .draw_string("{c:red}H{/c}i", Point::zero(), Baseline::Top, &mut display)
+            .unwrap();
+
+        let mut spans_display = MockDisplay::new();
+        let spans = [
+            ("H", SpanStyle::new(Rgb888::RED)),
+            ("i", SpanStyle::new(Rgb888::WHITE)),
+        ];
+        style
+            .draw_spans(&spans, Point::zero(), Baseline::Top, &mut spans_display)
+            .unwrap();
+        display.assert_eq(&spans_display);
+    }
+
+    #[test]
+    fn test_tags_apply_within_a_word_of_text_box() {
+        let style = TextStyle::new(Rgb888::WHITE);
+        let markup_style = MarkupStyle::new(style, &PALETTE);
+        let bounds = Rectangle::new(Point::zero(), Size::new(40, 11));
+        let draw = |text| {
+            let mut display = MockDisplay::new();
+            TextBox::new(text, bounds, markup_style)
+                .draw(&mut display)
+                .unwrap();
+            display
+        };
+
+        let mut red = style;
+        red.set_text_color(Some(Rgb888::RED));
+        let mut expected = MockDisplay::new();
+        TextBox::new("two words", bounds, red)
+            .draw(&mut expected)
+            .unwrap();
+        draw("{c:red}two{/c} {c:red}words{/c}").assert_eq(&expected);
+    }
+
+    #[test]
+    fn test_tags_span_words_and_lines_of_paragraph() {
+        let style = TextStyle::new(Rgb888::WHITE);
+        let markup_style = MarkupStyle::new(style, &PALETTE);
+        // The width only fits one word, so the tags continue on the next lines.
+        let width = style.measure_string("three", Point::zero(), Baseline::Top);
+        let paragraph = Paragraph::new(style, width.next_position.x as u32);
+        let mut display = MockDisplay::new();
+        let text = "{c:red}one two{/c} {u}three{/u}";
+        paragraph
+            .draw_runs(markup_style.markup(text), Point::zero(), &mut display)
+            .unwrap();
+
+        let mut red = style;
+        red.set_text_color(Some(Rgb888::RED));
+        let mut underlined = style;
+        underlined.set_underline_color(DecorationColor::TextColor);
+        let mut expected = MockDisplay::new();
+        for (style, text, y) in [(red, "one", 0), (red, "two", 11), (underlined, "three", 22)] {
+            style
+                .draw_string(text, Point::new(0, y), Baseline::Top, &mut expected)
+                .unwrap();
+        }
+        display.assert_eq(&expected);
+    }
+}
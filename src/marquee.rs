@@ -0,0 +1,184 @@
+use crate::{rotation::RotatedDrawTarget, text_style::TextStyle};
+use embedded_graphics::{
+    draw_target::{DrawTarget, DrawTargetExt},
+    geometry::{Point, Size},
+    pixelcolor::PixelColor,
+    primitives::Rectangle,
+    text::renderer::TextRenderer,
+};
+
+/// A horizontal ticker, that scrolls a line of text through a window of a fixed width.
+///
+/// The text repeats with a gap after it, so it wraps around seamlessly.
+/// Only the glyphs, that intersect the window, are drawn, so it is cheap to draw
+/// on every frame. The window is cleared with the background color of the text style.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Marquee<'a, C> {
+    style: TextStyle<C>,
+    text: &'a str,
+    text_width: u32,
+    width: u32,
+    gap: u32,
+    offset: u32,
+}
+
+impl<'a, C> Marquee<'a, C>
+where
+    C: PixelColor,
+{
+    /// Creates a new ticker with the text style, the text and the window width in pixels.
+    ///
+    /// The gap after the text is four spaces wide.
+    pub fn new(style: TextStyle<C>, text: &'a str, width: u32) -> Self {
+        Self {
+            style,
+            text,
+            text_width: style.advance_position(text, 0).max(0) as u32,
+            width,
+            gap: style.advance_position("    ", 0).max(0) as u32,
+            offset: 0,
+        }
+    }
+
+    /// Sets the text, and keeps the scroll offset if it is within the new text.
+    pub fn set_text(&mut self, text: &'a str) {
+        self.text = text;
+        self.text_width = self.style.advance_position(text, 0).max(0) as u32;
+        self.set_offset(self.offset);
+    }
+
+    /// Sets the gap between the end of the text and its next repetition in pixels.
+    pub fn set_gap(&mut self, gap: u32) {
+        self.gap = gap;
+        self.set_offset(self.offset);
+    }
+
+    /// Returns the distance in pixels, after which the text repeats.
+    pub fn period(&self) -> u32 {
+        self.text_width + self.gap
+    }
+
+    /// Returns the scroll offset in pixels.
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// Sets the scroll offset in pixels, that wraps around the period.
+    pub fn set_offset(&mut self, offset: u32) {
+        self.offset = offset.checked_rem(self.period()).unwrap_or(0);
+    }
+
+    /// Scrolls the text to the left by the number of pixels.
+    pub fn scroll(&mut self, pixels: u32) {
+        let period = self.period().max(1);
+        self.set_offset(self.offset + pixels % period);
+    }
+
+    /// Returns the size of the window in pixels.
+    pub fn size(&self) -> Size {
+        Size::new(self.width, self.style.line_height())
+    }
+
+    /// Draws the window with the top left corner at the position,
+    /// rotated around the rotation origin of the style.
+    pub fn draw<D>(&self, position: Point, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let target = &mut RotatedDrawTarget::new(
+            target,
+            self.style.rotation_origin(),
+            self.style.rotation(),
+        );
+        let window = Rectangle::new(position, self.size());
+        let target = &mut target.clipped(&window);
+        if let Some(color) = self.style.span_style().background_color {
+            target.fill_solid(&window, color)?;
+        }
+
+        let period = self.period() as i32;
+        if self.text.is_empty() || period == 0 {
+            return Ok(());
+        }
+        let mut x = -(self.offset as i32);
+        while x < self.width as i32 {
+            // The range of the window relative to this repetition of the text.
+            let visible = -x..self.width as i32 - x;
+            let text_position = position + Point::new(x, 0);
+            self.style
+                .draw_visible(self.text, text_position, visible, target)?;
+            x += period;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics::{
+        mock_display::MockDisplay,
+        pixelcolor::BinaryColor,
+        text::{renderer::CharacterStyle, Baseline, DecorationColor},
+    };
+
+    #[test]
+    fn test_scroll_wraps_around() {
+        let style = TextStyle::new(BinaryColor::On);
+        let mut marquee = Marquee::new(style, "Hi!", 20);
+        marquee.set_gap(4);
+        assert_eq!(marquee.period(), 12);
+        marquee.scroll(5);
+        assert_eq!(marquee.offset(), 5);
+        marquee.scroll(10);
+        assert_eq!(marquee.offset(), 3);
+        marquee.scroll(u32::MAX);
+        // u32::MAX is 3 modulo 12.
+        assert_eq!(marquee.offset(), 6);
+        marquee.set_text("");
+        marquee.set_gap(0);
+        marquee.scroll(1);
+        assert_eq!(marquee.offset(), 0);
+    }
+
+    #[test]
+    fn test_draw_repeated_text() {
+        let mut style = TextStyle::new(BinaryColor::On);
+        style.set_underline_color(DecorationColor::TextColor);
+        let mut marquee = Marquee::new(style, "Hi!", 20);
+        marquee.set_gap(4);
+        marquee.set_offset(5);
+        let position = Point::new(2, 3);
+        let mut display = MockDisplay::new();
+        marquee.draw(position, &mut display).unwrap();
+
+        let window = Rectangle::new(position, marquee.size());
+        let mut expected = MockDisplay::new();
+        expected.set_allow_overdraw(true);
+        for x in [-5, 7, 19] {
+            style
+                .draw_string(
+                    "Hi!",
+                    position + Point::new(x, 0),
+                    Baseline::Top,
+                    &mut expected.clipped(&window),
+                )
+                .unwrap();
+        }
+        display.assert_eq(&expected);
+    }
+
+    #[test]
+    fn test_clear_window_with_background() {
+        let mut style = TextStyle::new(BinaryColor::On);
+        style.set_background_color(Some(BinaryColor::Off));
+        let marquee = Marquee::new(style, "Hi!", 20);
+        let mut display = MockDisplay::new();
+        display.set_allow_overdraw(true);
+        marquee.draw(Point::zero(), &mut display).unwrap();
+        assert_eq!(
+            display.affected_area(),
+            Rectangle::new(Point::zero(), marquee.size())
+        );
+    }
+}
@@ -0,0 +1,533 @@
+use crate::text_style::{SpanStyle, TextStyle};
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::Point,
+    pixelcolor::PixelColor,
+    text::{renderer::TextRenderer, Baseline},
+};
+
+/// Horizontal alignment of the lines in a paragraph.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum ParagraphAlignment {
+    /// Lines start at the left edge.
+    #[default]
+    Left,
+    /// Lines are centred.
+    Center,
+    /// Lines end at the right edge.
+    Right,
+    /// Spaces are stretched, so the lines fill the whole width,
+    /// except for the last line of the paragraph.
+    Justified,
+}
+
+/// A paragraph layouter, that wraps the text into lines of a maximum width.
+///
+/// The lines are broken at the spaces and the hard line breaks, the words
+/// that are longer than the line are broken between the glyphs, never inside a ligature.
+/// The lines are stacked downwards, so the text style shouldn't be rotated.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Paragraph<C> {
+    style: TextStyle<C>,
+    width: u32,
+    alignment: ParagraphAlignment,
+    line_spacing: i32,
+    max_lines: Option<usize>,
+}
+
+impl<C> Paragraph<C>
+where
+    C: PixelColor,
+{
+    /// Creates a new paragraph layouter with the text style and the maximum line width in pixels.
+    pub fn new(style: TextStyle<C>, width: u32) -> Self {
+        Self {
+            style,
+            width,
+            alignment: ParagraphAlignment::Left,
+            line_spacing: 0,
+            max_lines: None,
+        }
+    }
+
+    /// Sets the horizontal alignment of the lines.
+    pub fn set_alignment(&mut self, alignment: ParagraphAlignment) {
+        self.alignment = alignment;
+    }
+
+    /// Sets the extra space between the lines in pixels, that can be negative.
+    pub fn set_line_spacing(&mut self, line_spacing: i32) {
+        self.line_spacing = line_spacing;
+    }
+
+    /// Sets the maximum number of lines, the rest of the text is not drawn.
+    pub fn set_max_lines(&mut self, max_lines: Option<usize>) {
+        self.max_lines = max_lines;
+    }
+
+    /// Returns an iterator over the wrapped lines of a text.
+    ///
+    /// The lines don't include the line breaks and the spaces where they are wrapped.
+    pub fn lines<'t>(&self, text: &'t str) -> impl Iterator<Item = &'t str> + 't
+    where
+        C: 't,
+    {
+        self.wrapped_lines(self.plain_runs(text))
+            .map(move |((_, start), (_, end), _)| &text[start..end])
+    }
+
+    /// Returns the height of the wrapped text in pixels.
+    pub fn height(&self, text: &str) -> u32 {
+        self.runs_height(self.plain_runs(text))
+    }
+
+    /// Returns the height of the wrapped styled runs of text in pixels.
+    pub fn runs_height<'t, I>(&self, runs: I) -> u32
+    where
+        I: Iterator<Item = (&'t str, SpanStyle<C>)> + Clone + 't,
+        C: 't,
+    {
+        match self.wrapped_lines(runs).count() as i32 {
+            0 => 0,
+            lines => (lines * self.line_height() - self.line_spacing) as u32,
+        }
+    }
+
+    /// Draws the wrapped text with the top left corner at the position,
+    /// and returns the position of the top left corner of the next line.
+    pub fn draw<D>(&self, text: &str, position: Point, target: &mut D) -> Result<Point, D::Error>
+    where
+        D: DrawTarget<Color = C>,
+    {
+        self.draw_runs(self.plain_runs(text), position, target)
+    }
+
+    /// Draws the wrapped styled runs of text with the top left corner at the position,
+    /// and returns the position of the top left corner of the next line.
+    ///
+    /// The runs come from a parser like [`Markup`](crate::Markup) or [`Ansi`](crate::Ansi),
+    /// so the styles continue across the words and the lines of the whole text,
+    /// unlike in a `TextBox`, that passes every word to the renderer separately.
+    pub fn draw_runs<'t, I, D>(
+        &self,
+        runs: I,
+        position: Point,
+        target: &mut D,
+    ) -> Result<Point, D::Error>
+    where
+        I: Iterator<Item = (&'t str, SpanStyle<C>)> + Clone + 't,
+        C: 't,
+        D: DrawTarget<Color = C>,
+    {
+        let mut position = position;
+        for (start, end, is_last) in self.wrapped_lines(runs.clone()) {
+            let line = runs_between(runs.clone(), start, end);
+            let line_width = self.runs_width(line.clone());
+            let free_width = self.width as i32 - line_width;
+            match self.alignment {
+                ParagraphAlignment::Left => self.draw_line(line, position, target)?,
+                ParagraphAlignment::Center => {
+                    let line_position = position + Point::new(free_width / 2, 0);
+                    self.draw_line(line, line_position, target)?;
+                }
+                ParagraphAlignment::Right => {
+                    let line_position = position + Point::new(free_width, 0);
+                    self.draw_line(line, line_position, target)?;
+                }
+                ParagraphAlignment::Justified => {
+                    let free_width = if is_last { 0 } else { free_width.max(0) };
+                    self.draw_justified(runs.clone(), (start, end), position, free_width, target)?;
+                }
+            }
+            position.y += self.line_height();
+        }
+        Ok(position)
+    }
+
+    /// Draws a line with the free width distributed between the spaces.
+    fn draw_justified<'t, I, D>(
+        &self,
+        runs: I,
+        (start, end): (RunOffset, RunOffset),
+        position: Point,
+        free_width: i32,
+        target: &mut D,
+    ) -> Result<(), D::Error>
+    where
+        I: Iterator<Item = (&'t str, SpanStyle<C>)> + Clone + 't,
+        C: 't,
+        D: DrawTarget<Color = C>,
+    {
+        let words = || words(runs_between(runs.clone(), start, end), start, end);
+        let gaps = words().count() as i32 - 1;
+        for (gap, (word_start, word_end)) in words().enumerate() {
+            let extra = match gaps {
+                0 => 0,
+                gaps => free_width * gap as i32 / gaps,
+            };
+            let x = self.runs_width(runs_between(runs.clone(), start, word_start)) + extra;
+            let word = runs_between(runs.clone(), word_start, word_end);
+            self.draw_line(word, position + Point::new(x, 0), target)?;
+        }
+        Ok(())
+    }
+
+    /// Draws the styled runs as one line with the top left corner at the position.
+    fn draw_line<'t, I, D>(&self, runs: I, position: Point, target: &mut D) -> Result<(), D::Error>
+    where
+        I: Iterator<Item = (&'t str, SpanStyle<C>)> + Clone,
+        D: DrawTarget<Color = C>,
+    {
+        let layout = &mut self.style.line_layout();
+        self.style
+            .draw_runs(runs, layout, position, Baseline::Top, target)
+            .map(drop)
+    }
+
+    /// Returns the width of the styled runs laid out as one line in pixels.
+    fn runs_width<'t, I>(&self, runs: I) -> i32
+    where
+        I: Iterator<Item = (&'t str, SpanStyle<C>)> + Clone,
+    {
+        self.style
+            .measure_runs(runs, Point::zero(), Baseline::Top)
+            .next_position
+            .x
+    }
+
+    /// Returns the plain text as a single run with the colors of the text style.
+    fn plain_runs<'t>(
+        &self,
+        text: &'t str,
+    ) -> impl Iterator<Item = (&'t str, SpanStyle<C>)> + Clone + 't
+    where
+        C: 't,
+    {
+        core::iter::once((text, self.style.span_style()))
+    }
+
+    /// Returns the distance between the tops of the lines in pixels.
+    fn line_height(&self) -> i32 {
+        self.style.line_height() as i32 + self.line_spacing
+    }
+
+    /// Returns an iterator over the start and the end of the wrapped lines of the styled runs,
+    /// together with true for the last lines of the paragraphs.
+    fn wrapped_lines<'t, I>(
+        &self,
+        runs: I,
+    ) -> impl Iterator<Item = (RunOffset, RunOffset, bool)> + 't
+    where
+        I: Iterator<Item = (&'t str, SpanStyle<C>)> + Clone + 't,
+        C: 't,
+    {
+        let style = self.style;
+        let width = self.width as i32;
+        let mut rest = runs
+            .clone()
+            .any(|(text, _)| !text.is_empty())
+            .then_some((0, 0));
+        core::iter::from_fn(move || {
+            let start = rest?;
+            let (paragraph_end, next_paragraph) = paragraph_end(runs.clone(), start);
+            let end = line_end(&style, runs.clone(), start, paragraph_end, width);
+            if end == paragraph_end {
+                rest = next_paragraph;
+                Some((start, paragraph_end, true))
+            } else {
+                rest = Some(skip_spaces(runs.clone(), end));
+                Some((start, trim_spaces(runs.clone(), start, end), false))
+            }
+        })
+        .take(self.max_lines.unwrap_or(usize::MAX))
+    }
+}
+
+/// A position in the styled runs of text: the index of the run and the byte offset in it.
+type RunOffset = (usize, usize);
+
+/// Returns the parts of the styled runs between the start and the end.
+fn runs_between<'t, C>(
+    runs: impl Iterator<Item = (&'t str, SpanStyle<C>)> + Clone,
+    start: RunOffset,
+    end: RunOffset,
+) -> impl Iterator<Item = (&'t str, SpanStyle<C>)> + Clone {
+    runs.enumerate()
+        .skip(start.0)
+        .take(end.0 + 1 - start.0)
+        .map(move |(index, (text, span))| {
+            let text_start = if index == start.0 { start.1 } else { 0 };
+            let text_end = if index == end.0 { end.1 } else { text.len() };
+            (&text[text_start..text_end], span)
+        })
+}
+
+/// Returns the end of the paragraph at the next line break after the start,
+/// and the start of the next paragraph if there is a line break.
+fn paragraph_end<'t, C>(
+    runs: impl Iterator<Item = (&'t str, SpanStyle<C>)>,
+    start: RunOffset,
+) -> (RunOffset, Option<RunOffset>) {
+    let mut end = start;
+    // The carriage return before the line break can be at the end of the previous run.
+    let mut ends_with_cr = false;
+    for (index, (text, _)) in runs.enumerate().skip(start.0) {
+        let text_start = if index == start.0 { start.1 } else { 0 };
+        if let Some(line_break) = text[text_start..].find('\n') {
+            let next_paragraph = Some((index, text_start + line_break + 1));
+            if line_break == 0 && ends_with_cr {
+                return ((end.0, end.1 - 1), next_paragraph);
+            }
+            let line = &text[text_start..text_start + line_break];
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            return ((index, text_start + line.len()), next_paragraph);
+        }
+        if !text[text_start..].is_empty() {
+            end = (index, text.len());
+            ends_with_cr = text.ends_with('\r');
+        }
+    }
+    (end, None)
+}
+
+/// Returns the end of the line, where the styled runs without the line breaks are wrapped.
+///
+/// Prefers to break at the spaces, otherwise breaks between the glyphs,
+/// but always keeps at least one glyph in the line. Like with the truncation,
+/// the oblique overhang and the effect of the last glyph must fit into the width.
+fn line_end<'t, C>(
+    style: &TextStyle<C>,
+    runs: impl Iterator<Item = (&'t str, SpanStyle<C>)> + Clone + 't,
+    start: RunOffset,
+    end: RunOffset,
+    width: i32,
+) -> RunOffset
+where
+    C: PixelColor + 't,
+{
+    let width = width - style.trailing_overhang();
+    let line = runs_between(runs, start, end);
+    let mut texts = line.clone().map(|(text, _)| text).enumerate();
+    let mut text = None;
+    let mut space_end = None;
+    let mut glyph_end = None;
+    for (index, glyph) in style.layout_runs(line) {
+        while text.is_none_or(|(i, _)| i != index) {
+            text = texts.next();
+        }
+        let text = text.map_or("", |(_, text)| text);
+        // The byte offsets in the first run are relative to the start.
+        let offset = |byte| match index {
+            0 => (start.0, start.1 + byte),
+            index => (start.0 + index, byte),
+        };
+        let bytes = glyph.bytes();
+        if text[bytes.clone()].starts_with(' ') {
+            space_end = Some(offset(bytes.start));
+        }
+        if glyph.position().x + glyph.advance() > width {
+            return space_end
+                .filter(|&end| end != start)
+                .or(glyph_end)
+                .unwrap_or(offset(bytes.end));
+        }
+        glyph_end = Some(offset(bytes.end));
+    }
+    end
+}
+
+/// Returns the end of the line without the trailing spaces.
+fn trim_spaces<'t, C>(
+    runs: impl Iterator<Item = (&'t str, SpanStyle<C>)> + Clone,
+    start: RunOffset,
+    end: RunOffset,
+) -> RunOffset {
+    runs_between(runs, start, end)
+        .enumerate()
+        .filter_map(|(index, (text, _))| {
+            let len = text.trim_end_matches(' ').len();
+            match index {
+                _ if len == 0 => None,
+                0 => Some((start.0, start.1 + len)),
+                index => Some((start.0 + index, len)),
+            }
+        })
+        .last()
+        .unwrap_or(start)
+}
+
+/// Returns the position after the spaces at the start, that can span several runs.
+fn skip_spaces<'t, C>(
+    runs: impl Iterator<Item = (&'t str, SpanStyle<C>)>,
+    start: RunOffset,
+) -> RunOffset {
+    let mut end = start;
+    for (index, (text, _)) in runs.enumerate().skip(start.0) {
+        let text_start = if index == start.0 { start.1 } else { 0 };
+        let rest = text[text_start..].trim_start_matches(' ');
+        end = (index, text.len() - rest.len());
+        if !rest.is_empty() {
+            break;
+        }
+    }
+    end
+}
+
+/// Returns an iterator over the start and the end of the words of a line,
+/// that are separated by the spaces, and can span several runs.
+fn words<'t, C: 't>(
+    line: impl Iterator<Item = (&'t str, SpanStyle<C>)> + 't,
+    start: RunOffset,
+    end: RunOffset,
+) -> impl Iterator<Item = (RunOffset, RunOffset)> + 't {
+    let mut chars = line
+        .enumerate()
+        .flat_map(move |(index, (text, _))| {
+            text.char_indices().map(move |(byte, char)| match index {
+                0 => ((start.0, start.1 + byte), char),
+                index => ((start.0 + index, byte), char),
+            })
+        })
+        .peekable();
+    core::iter::from_fn(move || {
+        while chars.next_if(|&(_, char)| char == ' ').is_some() {}
+        let (word_start, _) = chars.next()?;
+        let word_end = chars
+            .find(|&(_, char)| char == ' ')
+            .map_or(end, |(offset, _)| offset);
+        Some((word_start, word_end))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TextEffect;
+    use embedded_graphics::{mock_display::MockDisplay, pixelcolor::BinaryColor};
+
+    fn assert_lines(paragraph: &Paragraph<BinaryColor>, text: &str, expected: &[&str]) {
+        assert!(paragraph.lines(text).eq(expected.iter().copied()));
+    }
+
+    #[test]
+    fn test_word_wrapping() {
+        let style = TextStyle::new(BinaryColor::On);
+        let width = style.advance_position("Hello, big", 0) as u32;
+        let paragraph = Paragraph::new(style, width);
+        assert_lines(
+            &paragraph,
+            "Hello, big world!\nBye",
+            &["Hello, big", "world!", "Bye"],
+        );
+        assert_lines(&paragraph, "", &[]);
+        assert_lines(&paragraph, "a\n\nb", &["a", "", "b"]);
+    }
+
+    #[test]
+    fn test_break_long_words_between_glyphs() {
+        let style = TextStyle::new(BinaryColor::On);
+        // "ffi" is a ligature that fits into the line, but "affi" doesn't
+        let width = style.advance_position("ffi", 0) as u32;
+        let paragraph = Paragraph::new(style, width);
+        assert_lines(&paragraph, "affix", &["a", "ffi", "x"]);
+        // At least one glyph is kept in every line
+        let paragraph = Paragraph::new(style, 0);
+        assert_lines(&paragraph, "ab", &["a", "b"]);
+    }
+
+    #[test]
+    fn test_wrap_oblique_and_outlined_text() {
+        let mut oblique = TextStyle::new(BinaryColor::On);
+        oblique.set_oblique(4);
+        let mut outlined = TextStyle::new(BinaryColor::On);
+        outlined.set_effect(TextEffect::Outline(BinaryColor::Off));
+        for style in [oblique, outlined] {
+            // The last glyph sticks out of its advance.
+            let width = style.advance_position("Hello, big", 0) as u32;
+            let paragraph = Paragraph::new(style, width);
+            assert_lines(
+                &paragraph,
+                "Hello, big world!",
+                &["Hello,", "big", "world!"],
+            );
+            let width = width + style.trailing_overhang() as u32;
+            let paragraph = Paragraph::new(style, width);
+            assert_lines(&paragraph, "Hello, big world!", &["Hello, big", "world!"]);
+        }
+    }
+
+    #[test]
+    fn test_max_lines_and_height() {
+        let style = TextStyle::new(BinaryColor::On);
+        let mut paragraph = Paragraph::new(style, 100);
+        paragraph.set_line_spacing(2);
+        assert_eq!(paragraph.height("one\ntwo\nthree"), 11 * 3 + 2 * 2);
+        paragraph.set_max_lines(Some(2));
+        assert_lines(&paragraph, "one\ntwo\nthree", &["one", "two"]);
+        assert_eq!(paragraph.height("one\ntwo\nthree"), 11 * 2 + 2);
+        assert_eq!(paragraph.height(""), 0);
+    }
+
+    #[test]
+    fn test_draw_alignment() {
+        let style = TextStyle::new(BinaryColor::On);
+        let text = "Hi all, hi you";
+        let width = style.advance_position(text, 0);
+        let mut paragraph = Paragraph::new(style, width as u32 + 10);
+        for (alignment, left, lines) in [
+            (ParagraphAlignment::Left, 0, 1),
+            (ParagraphAlignment::Center, 5, 1),
+            (ParagraphAlignment::Right, 10, 1),
+        ] {
+            paragraph.set_alignment(alignment);
+            let mut display = MockDisplay::new();
+            let result = paragraph.draw(text, Point::zero(), &mut display);
+            assert_eq!(result, Ok(Point::new(0, 11 * lines)));
+            let area = display.affected_area();
+            assert_eq!(area.top_left.x, left, "{alignment:?}");
+            assert_eq!(area.size.width as i32, width, "{alignment:?}");
+        }
+
+        // The last line is not justified
+        paragraph.set_alignment(ParagraphAlignment::Justified);
+        let mut display = MockDisplay::new();
+        let result = paragraph.draw("Hi all, hi you all", Point::zero(), &mut display);
+        assert_eq!(result, Ok(Point::new(0, 22)));
+        let area = display.affected_area();
+        assert_eq!(area.top_left.x, 0);
+        assert_eq!(area.size.width as i32, width + 10);
+    }
+
+    #[test]
+    fn test_draw_runs_same_as_text() {
+        let style = TextStyle::new(BinaryColor::On);
+        let text = "Hi all,  hi you\r\nall";
+        let span = style.span_style();
+        // The runs are split inside the words, the spaces and the line break.
+        let runs = [
+            (&text[..4], span),
+            (&text[4..8], span),
+            (&text[8..16], span),
+            (&text[16..], span),
+        ];
+        let width = style.advance_position("Hi all, hi", 0) as u32;
+        let mut paragraph = Paragraph::new(style, width);
+        for alignment in [
+            ParagraphAlignment::Left,
+            ParagraphAlignment::Center,
+            ParagraphAlignment::Right,
+            ParagraphAlignment::Justified,
+        ] {
+            paragraph.set_alignment(alignment);
+            let mut display = MockDisplay::new();
+            let result = paragraph.draw_runs(runs.iter().copied(), Point::zero(), &mut display);
+            let mut expected = MockDisplay::new();
+            assert_eq!(result, paragraph.draw(text, Point::zero(), &mut expected));
+            display.assert_eq(&expected);
+        }
+        assert_eq!(
+            paragraph.runs_height(runs.iter().copied()),
+            paragraph.height(text)
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::{Dimensions, Point},
+    primitives::Rectangle,
+    Pixel,
+};
+
+/// Clockwise rotation of the text around the rotation origin.
+///
+/// The text is laid out horizontally, and then rotated around the pixel at the rotation origin,
+/// e.g. with [`Rotate90`](Rotation::Rotate90) the text reads from top to bottom.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rotation {
+    /// No rotation.
+    #[default]
+    Rotate0,
+    /// Rotation by 90 degrees clockwise.
+    Rotate90,
+    /// Rotation by 180 degrees.
+    Rotate180,
+    /// Rotation by 270 degrees clockwise.
+    Rotate270,
+}
+
+impl Rotation {
+    /// Returns the rotation that undoes this rotation.
+    pub(crate) fn inverse(self) -> Self {
+        match self {
+            Rotation::Rotate0 => Rotation::Rotate0,
+            Rotation::Rotate90 => Rotation::Rotate270,
+            Rotation::Rotate180 => Rotation::Rotate180,
+            Rotation::Rotate270 => Rotation::Rotate90,
+        }
+    }
+
+    /// Rotates a point around the origin.
+    pub(crate) fn rotate_point(self, point: Point, origin: Point) -> Point {
+        let Point { x, y } = point - origin;
+        origin
+            + match self {
+                Rotation::Rotate0 => Point::new(x, y),
+                Rotation::Rotate90 => Point::new(-y, x),
+                Rotation::Rotate180 => Point::new(-x, -y),
+                Rotation::Rotate270 => Point::new(y, -x),
+            }
+    }
+
+    /// Rotates the pixels of a rectangle around the origin.
+    pub(crate) fn rotate_rectangle(self, rectangle: Rectangle, origin: Point) -> Rectangle {
+        match rectangle.bottom_right() {
+            Some(bottom_right) => Rectangle::with_corners(
+                self.rotate_point(rectangle.top_left, origin),
+                self.rotate_point(bottom_right, origin),
+            ),
+            None => Rectangle::zero(),
+        }
+    }
+}
+
+/// A draw target that rotates everything drawn to it around the origin.
+///
+/// [`TextBox`](https://docs.rs/embedded-text) clips every line to the text box
+/// before the text is drawn, so instead of rotating the text style,
+/// draw the text box with an unrotated style into the rotated draw target.
+#[derive(Debug)]
+pub struct RotatedDrawTarget<'a, T> {
+    parent: &'a mut T,
+    origin: Point,
+    rotation: Rotation,
+}
+
+impl<'a, T: DrawTarget> RotatedDrawTarget<'a, T> {
+    /// Creates a draw target that rotates the pixels around the origin before drawing them to the parent.
+    pub fn new(parent: &'a mut T, origin: Point, rotation: Rotation) -> Self {
+        Self {
+            parent,
+            origin,
+            rotation,
+        }
+    }
+}
+
+impl<T: DrawTarget> DrawTarget for RotatedDrawTarget<'_, T> {
+    type Color = T::Color;
+    type Error = T::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let (origin, rotation) = (self.origin, self.rotation);
+        self.parent.draw_iter(
+            pixels
+                .into_iter()
+                .map(|Pixel(point, color)| Pixel(rotation.rotate_point(point, origin), color)),
+        )
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let area = self.rotation.rotate_rectangle(*area, self.origin);
+        self.parent.fill_solid(&area, color)
+    }
+}
+
+impl<T: DrawTarget> Dimensions for RotatedDrawTarget<'_, T> {
+    fn bounding_box(&self) -> Rectangle {
+        self.rotation
+            .inverse()
+            .rotate_rectangle(self.parent.bounding_box(), self.origin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics::geometry::Size;
+
+    #[test]
+    fn test_rotate_rectangle() {
+        let origin = Point::new(10, 10);
+        let rectangle = Rectangle::new(Point::new(10, 8), Size::new(4, 2));
+        assert_eq!(
+            Rotation::Rotate90.rotate_rectangle(rectangle, origin),
+            Rectangle::new(Point::new(11, 10), Size::new(2, 4))
+        );
+        assert_eq!(
+            Rotation::Rotate180.rotate_rectangle(rectangle, origin),
+            Rectangle::new(Point::new(7, 11), Size::new(4, 2))
+        );
+        assert_eq!(
+            Rotation::Rotate270.rotate_rectangle(rectangle, origin),
+            Rectangle::new(Point::new(8, 7), Size::new(2, 4))
+        );
+    }
+
+    #[test]
+    fn test_inverse() {
+        let point = Point::new(3, -5);
+        for rotation in [
+            Rotation::Rotate0,
+            Rotation::Rotate90,
+            Rotation::Rotate180,
+            Rotation::Rotate270,
+        ] {
+            let rotated = rotation.rotate_point(point, Point::new(1, 1));
+            assert_eq!(
+                rotation.inverse().rotate_point(rotated, Point::new(1, 1)),
+                point
+            );
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use crate::text_style::{SpanStyle, TextStyle};
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::Point,
+    pixelcolor::PixelColor,
+    text::{
+        renderer::{CharacterStyle, TextMetrics, TextRenderer},
+        Baseline, DecorationColor,
+    },
+};
+
+/// A parser that splits a text into styled runs, e.g. the palette of [`Markup`](crate::Markup)
+/// or [`Ansi`](crate::Ansi).
+pub trait Runs<C> {
+    /// The iterator over the styled runs.
+    type Iter<'t>: Iterator<Item = (&'t str, SpanStyle<C>)> + Clone
+    where
+        Self: 't;
+
+    /// Splits the text into styled runs, that start with the style.
+    fn runs<'t>(&'t self, text: &'t str, style: SpanStyle<C>) -> Self::Iter<'t>;
+}
+
+/// A text renderer for the text, that is split into styled runs by the parser.
+///
+/// The runs are drawn with their own styles as one continuous line,
+/// and the markup between them is not included in the measured text.
+/// The style of the runs only applies until the end of the string passed to the renderer:
+/// a [`Text`](embedded_graphics::text::Text) passes every line separately,
+/// and a `TextBox` passes every word separately.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RunStyle<C, R> {
+    style: TextStyle<C>,
+    runs: R,
+}
+
+impl<C, R> RunStyle<C, R>
+where
+    C: PixelColor,
+    R: Runs<C>,
+{
+    /// Returns the text style.
+    pub fn style(&self) -> &TextStyle<C> {
+        &self.style
+    }
+
+    /// Returns the styled runs of the text.
+    pub fn runs<'t>(&'t self, text: &'t str) -> R::Iter<'t> {
+        self.runs.runs(text, self.style.span_style())
+    }
+}
+
+impl<C, R> RunStyle<C, R> {
+    pub(crate) fn with_runs(style: TextStyle<C>, runs: R) -> Self {
+        Self { style, runs }
+    }
+}
+
+impl<C, R> TextRenderer for RunStyle<C, R>
+where
+    C: PixelColor,
+    R: Runs<C>,
+{
+    type Color = C;
+
+    fn draw_string<D>(
+        &self,
+        text: &str,
+        position: Point,
+        baseline: Baseline,
+        target: &mut D,
+    ) -> Result<Point, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        let layout = &mut self.style.line_layout();
+        self.style
+            .draw_runs(self.runs(text), layout, position, baseline, target)
+    }
+
+    fn draw_whitespace<D>(
+        &self,
+        width: u32,
+        position: Point,
+        baseline: Baseline,
+        target: &mut D,
+    ) -> Result<Point, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        self.style
+            .draw_whitespace(width, position, baseline, target)
+    }
+
+    fn measure_string(&self, text: &str, position: Point, baseline: Baseline) -> TextMetrics {
+        self.style.measure_runs(self.runs(text), position, baseline)
+    }
+
+    fn line_height(&self) -> u32 {
+        self.style.line_height()
+    }
+}
+
+impl<C, R> CharacterStyle for RunStyle<C, R>
+where
+    C: PixelColor,
+    R: Clone,
+{
+    type Color = C;
+
+    /// Sets the text color outside of the styled runs.
+    fn set_text_color(&mut self, text_color: Option<Self::Color>) {
+        self.style.set_text_color(text_color);
+    }
+
+    /// Sets the background color outside of the styled runs.
+    fn set_background_color(&mut self, background_color: Option<Self::Color>) {
+        self.style.set_background_color(background_color);
+    }
+
+    /// Sets the underline color outside of the styled runs.
+    fn set_underline_color(&mut self, underline_color: DecorationColor<Self::Color>) {
+        self.style.set_underline_color(underline_color);
+    }
+
+    /// Sets the strikethrough color outside of the styled runs.
+    fn set_strikethrough_color(&mut self, strikethrough_color: DecorationColor<Self::Color>) {
+        self.style.set_strikethrough_color(strikethrough_color);
+    }
+}
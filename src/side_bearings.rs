@@ -1,15 +1,22 @@
-use crate::charset::GlyphIndex;
+use crate::{charset::GlyphIndex, table::Table};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct SideBearings<'a> {
-    bearings: &'a [(u32, i32, i32)],
+    pub(crate) bearings: Table<'a, (u32, i32, i32)>,
     default_bearings: (i32, i32),
 }
 
 impl<'a> SideBearings<'a> {
     pub const fn new(bearings: &'a [(u32, i32, i32)], default_bearings: (i32, i32)) -> Self {
         Self {
-            bearings,
+            bearings: Table::Slice(bearings),
+            default_bearings,
+        }
+    }
+
+    pub const fn from_bytes(bearings: &'a [u8], default_bearings: (i32, i32)) -> Self {
+        Self {
+            bearings: Table::Bytes(bearings),
             default_bearings,
         }
     }
@@ -17,16 +24,24 @@ impl<'a> SideBearings<'a> {
     pub fn left(&self, index: GlyphIndex) -> i32 {
         self.bearings
             .binary_search_by_key(&(index.0 as u32), |data| data.0)
-            .map(|idx| self.bearings[idx].1)
+            .map(|idx| self.bearings.get(idx).1)
             .unwrap_or(self.default_bearings.0)
     }
 
     pub fn right(&self, index: GlyphIndex) -> i32 {
         self.bearings
             .binary_search_by_key(&(index.0 as u32), |data| data.0)
-            .map(|idx| self.bearings[idx].2)
+            .map(|idx| self.bearings.get(idx).2)
             .unwrap_or(self.default_bearings.1)
     }
+
+    pub fn default_left(&self) -> i32 {
+        self.default_bearings.0
+    }
+
+    pub fn default_right(&self) -> i32 {
+        self.default_bearings.1
+    }
 }
 
 #[cfg(test)]
@@ -45,5 +60,8 @@ mod tests {
 
         assert_eq!(bearings.left(GlyphIndex(2)), 5);
         assert_eq!(bearings.right(GlyphIndex(2)), 6);
+
+        assert_eq!(bearings.default_left(), 5);
+        assert_eq!(bearings.default_right(), 6);
     }
 }
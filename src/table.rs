@@ -0,0 +1,131 @@
+use core::cmp::Ordering;
+
+/// A sorted table of fixed size records.
+///
+/// The records are either stored in a slice, for the charsets that are compiled in,
+/// or encoded in little-endian bytes, for the charsets that are loaded at runtime.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Table<'a, T> {
+    Slice(&'a [T]),
+    Bytes(&'a [u8]),
+}
+
+/// A record that can be decoded from a fixed number of bytes.
+pub trait Record: Copy {
+    /// The number of bytes in an encoded record.
+    const SIZE: usize;
+
+    /// Decodes the record from exactly `SIZE` bytes.
+    fn decode(bytes: &[u8]) -> Self;
+}
+
+impl<T: Record> Table<'_, T> {
+    /// Returns the number of records.
+    pub fn len(&self) -> usize {
+        match self {
+            Table::Slice(records) => records.len(),
+            Table::Bytes(bytes) => bytes.len() / T::SIZE,
+        }
+    }
+
+    /// Returns false if the bytes end with a partial record.
+    pub fn has_whole_records(&self) -> bool {
+        match self {
+            Table::Slice(_) => true,
+            Table::Bytes(bytes) => bytes.len() % T::SIZE == 0,
+        }
+    }
+
+    /// Returns the record at the index.
+    pub fn get(&self, index: usize) -> T {
+        match self {
+            Table::Slice(records) => records[index],
+            Table::Bytes(bytes) => T::decode(&bytes[index * T::SIZE..(index + 1) * T::SIZE]),
+        }
+    }
+
+    /// Binary searches the table with a key extraction function,
+    /// the same way as `slice::binary_search_by_key`.
+    pub fn binary_search_by_key<K: Ord>(
+        &self,
+        key: &K,
+        f: impl Fn(&T) -> K,
+    ) -> Result<usize, usize> {
+        let mut low = 0;
+        let mut high = self.len();
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match f(&self.get(mid)).cmp(key) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => return Ok(mid),
+            }
+        }
+        Err(low)
+    }
+
+    /// Returns true if the keys of the records are strictly increasing,
+    /// which is required for the binary search.
+    pub fn is_sorted_by_key<K: Ord>(&self, f: impl Fn(&T) -> K) -> bool {
+        (1..self.len()).all(|i| f(&self.get(i - 1)) < f(&self.get(i)))
+    }
+}
+
+/// Side bearings: glyph index as u16, left and right bearings as i8.
+impl Record for (u32, i32, i32) {
+    const SIZE: usize = 4;
+
+    fn decode(bytes: &[u8]) -> Self {
+        (
+            u16::from_le_bytes([bytes[0], bytes[1]]) as u32,
+            bytes[2] as i8 as i32,
+            bytes[3] as i8 as i32,
+        )
+    }
+}
+
+/// Kerning pairs: left and right kerning classes as u8, kerning as i8.
+impl Record for (u8, u8, i32) {
+    const SIZE: usize = 3;
+
+    fn decode(bytes: &[u8]) -> Self {
+        (bytes[0], bytes[1], bytes[2] as i8 as i32)
+    }
+}
+
+/// Kerning overrides: left and right glyph indices as u16, kerning as i8.
+impl Record for (usize, usize, i32) {
+    const SIZE: usize = 5;
+
+    fn decode(bytes: &[u8]) -> Self {
+        (
+            u16::from_le_bytes([bytes[0], bytes[1]]) as usize,
+            u16::from_le_bytes([bytes[2], bytes[3]]) as usize,
+            bytes[4] as i8 as i32,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_search() {
+        let slice = Table::Slice(&[(1, 2, 0), (1, 3, -1), (4, 1, -2)]);
+        let bytes = Table::<(u8, u8, i32)>::Bytes(&[1, 2, 0, 1, 3, 0xFF, 4, 1, 0xFE]);
+        for table in [slice, bytes] {
+            assert_eq!(table.len(), 3);
+            assert_eq!(table.get(1), (1, 3, -1));
+            assert_eq!(table.binary_search_by_key(&(4, 1), |r| (r.0, r.1)), Ok(2));
+            assert_eq!(table.binary_search_by_key(&(2, 1), |r| (r.0, r.1)), Err(2));
+            assert!(table.is_sorted_by_key(|r| (r.0, r.1)));
+        }
+    }
+
+    #[test]
+    fn test_decode_side_bearings() {
+        let table = Table::<(u32, i32, i32)>::Bytes(&[0x2C, 0x01, 0xFE, 0x01]);
+        assert_eq!(table.get(0), (300, -2, 1));
+    }
+}
@@ -1,10 +1,18 @@
-use crate::{charset::Charset, draw_target::MogeeFontDrawTarget, generated::ASCII};
+use crate::{
+    charset::Charset,
+    draw_target::MogeeFontDrawTarget,
+    fallback_chain::{FallbackChain, Glyph},
+    generated::ASCII,
+    layout::PositionedGlyph,
+    rotation::{RotatedDrawTarget, Rotation},
+};
+use core::ops::Range;
 use embedded_graphics::{
     draw_target::{DrawTarget, DrawTargetExt},
     geometry::{Point, Size},
     image::ImageDrawable,
     pixelcolor::{BinaryColor, PixelColor},
-    primitives::{PrimitiveStyle, Rectangle, StyledDrawable},
+    primitives::Rectangle,
     text::{
         renderer::{CharacterStyle, TextMetrics, TextRenderer},
         Baseline, DecorationColor,
@@ -23,212 +31,2225 @@ pub struct TextStyle<C> {
     /// Background color.
     background_color: Option<C>,
 
-    /// Charset to use.
-    charset: &'static Charset,
+    /// Underline color.
+    underline_color: DecorationColor<C>,
+
+    /// Strikethrough color.
+    strikethrough_color: DecorationColor<C>,
+
+    /// Charsets to use.
+    fallback_chain: FallbackChain<'static>,
+
+    /// Integer scale factor.
+    scale: u8,
+
+    /// Rotation around the rotation origin.
+    rotation: Rotation,
+
+    /// The point that the text is rotated around.
+    rotation_origin: Point,
+
+    /// Synthetic bold.
+    bold: bool,
+
+    /// Number of rows per one pixel of the oblique shear, or 0 for the upright text.
+    oblique: u8,
+
+    /// Text effect.
+    effect: TextEffect<C>,
+
+    /// Extra space after every character.
+    letter_spacing: i8,
+
+    /// Extra space after every space character.
+    word_spacing: i8,
+
+    /// Ligature substitution.
+    ligatures: bool,
+
+    /// Kerning between glyph pairs.
+    kerning: bool,
+
+    /// Spacing of the digits.
+    figures: Figures,
+}
+
+/// A glyph in a line of text.
+#[derive(Copy, Clone, Debug)]
+struct LineGlyph {
+    glyph: Glyph<'static>,
+    /// Byte offset of the first character of the glyph in the text.
+    byte_offset: usize,
+    /// Horizontal offset from the start of the line in font units.
+    offset: i32,
+    /// Advance in font units, that includes the letter spacing,
+    /// and the word spacing for spaces.
+    advance: i32,
+    /// Synthetic bold, that smears the glyph one pixel to the right.
+    bold: bool,
+}
+
+impl LineGlyph {
+    /// Returns the width of the glyph, including the extra column of the bold text.
+    fn width(&self) -> i32 {
+        self.glyph.width() + self.bold as i32
+    }
+}
+
+/// The state of laying out the glyphs in a line, that can continue across several texts.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct LineLayout {
+    fallback_chain: FallbackChain<'static>,
+    letter_spacing: i32,
+    word_spacing: i32,
+    kerning: bool,
+    figures: Figures,
+    /// Horizontal offset after the last glyph from the start of the line in font units.
+    x: i32,
+    /// The last glyph, that the next glyph is kerned with.
+    prev_glyph: Option<Glyph<'static>>,
+    /// True if the last glyph has a tabular advance.
+    prev_tabular: bool,
+}
+
+impl LineLayout {
+    /// Places the next glyph in the line, the glyph is at the byte offset of the text.
+    fn place(
+        &mut self,
+        text: &str,
+        byte_offset: usize,
+        bold: bool,
+        glyph: Glyph<'static>,
+    ) -> LineGlyph {
+        let prev_tabular = self.prev_tabular;
+        // Ligatures are never tabular, even if they start with a digit.
+        let tabular = glyph.index.0 < glyph.charset.ligatures.offset
+            && text[byte_offset..]
+                .chars()
+                .next()
+                .is_some_and(|char| self.figures.is_tabular(char));
+        let offset = if tabular {
+            // Tabular glyphs are centred in their cells, without the side bearings
+            // and kerning, so they don't move when the neighbouring digits change.
+            if let Some(prev) = self.prev_glyph.filter(|_| !prev_tabular) {
+                self.x += prev.charset.side_bearings.right(prev.index);
+            }
+            let advance = glyph.charset.tabular_advance();
+            let offset = self.x + (advance - glyph.width()) / 2;
+            self.x += advance - glyph.width();
+            offset
+        } else {
+            let prev_glyph = self.prev_glyph.filter(|_| !prev_tabular);
+            self.x += self.fallback_chain.spacing(prev_glyph, glyph, self.kerning);
+            self.x
+        };
+        self.x += glyph.width() + bold as i32 + self.letter_spacing;
+        if self.word_spacing != 0 && glyph.index == glyph.charset.glyph_index(' ') {
+            self.x += self.word_spacing;
+        }
+        self.prev_glyph = Some(glyph);
+        self.prev_tabular = tabular;
+        LineGlyph {
+            glyph,
+            byte_offset,
+            offset,
+            advance: self.x - offset,
+            bold,
+        }
+    }
+}
+
+/// Spacing of the digits.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Figures {
+    /// Digits with their own widths and kerning.
+    #[default]
+    Proportional,
+    /// Digits centred in cells of the same width, that fits the widest digit,
+    /// so the numbers can be updated in place.
+    Tabular,
+    /// Tabular digits, together with '.', ':', '-' and '+'.
+    TabularWithPunctuation,
+}
+
+impl Figures {
+    /// Returns true if the character has a tabular advance.
+    fn is_tabular(self, char: char) -> bool {
+        match self {
+            Figures::Proportional => false,
+            Figures::Tabular => char.is_ascii_digit(),
+            Figures::TabularWithPunctuation => {
+                char.is_ascii_digit() || matches!(char, '.' | ':' | '-' | '+')
+            }
+        }
+    }
+}
+
+/// Text effect, that keeps the text legible over images and charts.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TextEffect<C> {
+    /// No effect.
+    None,
+    /// One pixel drop shadow to the bottom right of the glyphs.
+    Shadow(C),
+    /// One pixel outline, or halo, around the glyphs, including the diagonal neighbours.
+    Outline(C),
+}
+
+impl<C: Copy> TextEffect<C> {
+    /// Returns the color of the effect.
+    fn color(&self) -> Option<C> {
+        match self {
+            TextEffect::None => None,
+            TextEffect::Shadow(color) | TextEffect::Outline(color) => Some(*color),
+        }
+    }
+}
+
+impl<C> TextEffect<C> {
+    /// Returns the offsets of the copies of the glyph, that make the effect.
+    fn offsets(&self) -> &'static [Point] {
+        const SHADOW: [Point; 1] = [Point::new(1, 1)];
+        const OUTLINE: [Point; 8] = [
+            Point::new(-1, -1),
+            Point::new(0, -1),
+            Point::new(1, -1),
+            Point::new(-1, 0),
+            Point::new(1, 0),
+            Point::new(-1, 1),
+            Point::new(0, 1),
+            Point::new(1, 1),
+        ];
+        match self {
+            TextEffect::None => &[],
+            TextEffect::Shadow(_) => &SHADOW,
+            TextEffect::Outline(_) => &OUTLINE,
+        }
+    }
+
+    /// Returns how far the effect sticks out of the glyphs
+    /// beyond the top left and the bottom right corners.
+    fn extent(&self) -> (Point, Point) {
+        self.offsets().iter().fold(
+            (Point::zero(), Point::zero()),
+            |(top_left, bottom_right), &offset| {
+                (
+                    top_left.component_max(-offset),
+                    bottom_right.component_max(offset),
+                )
+            },
+        )
+    }
+}
+
+/// Colors of a span of rich text, drawn with [`TextStyle::draw_spans`].
+///
+/// The charsets, scale, spacing and effect of the text come from the text style.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct SpanStyle<C> {
+    /// Text color.
+    pub text_color: Option<C>,
+
+    /// Background color.
+    pub background_color: Option<C>,
+
+    /// Underline color.
+    pub underline_color: DecorationColor<C>,
+
+    /// Strikethrough color.
+    pub strikethrough_color: DecorationColor<C>,
+
+    /// Synthetic bold, in addition to the bold of the text style.
+    pub bold: bool,
+}
+
+impl<C> SpanStyle<C> {
+    /// Creates a new span style with the given text color.
+    pub fn new(text_color: C) -> Self {
+        Self {
+            text_color: Some(text_color),
+            ..Self::default()
+        }
+    }
+
+    /// Returns the color of a text decoration.
+    fn decoration_color(&self, decoration_color: DecorationColor<C>) -> Option<C>
+    where
+        C: Copy,
+    {
+        match decoration_color {
+            DecorationColor::None => None,
+            DecorationColor::TextColor => self.text_color,
+            DecorationColor::Custom(color) => Some(color),
+        }
+    }
+}
+
+impl<C> Default for SpanStyle<C> {
+    fn default() -> Self {
+        Self {
+            text_color: None,
+            background_color: None,
+            underline_color: DecorationColor::None,
+            strikethrough_color: DecorationColor::None,
+            bold: false,
+        }
+    }
 }
 
-impl<C> TextStyle<C> {
-    /// Creates a new text style with the given text color and the default ASCII charset.
-    pub fn new(text_color: C) -> Self {
-        Self {
-            text_color: Some(text_color),
-            background_color: None,
-            charset: &ASCII,
-        }
+impl<C> TextStyle<C> {
+    /// Creates a new text style with the given text color and the default ASCII charset.
+    pub fn new(text_color: C) -> Self {
+        Self {
+            text_color: Some(text_color),
+            background_color: None,
+            underline_color: DecorationColor::None,
+            strikethrough_color: DecorationColor::None,
+            fallback_chain: FallbackChain::new(core::slice::from_ref(&ASCII)),
+            scale: 1,
+            rotation: Rotation::Rotate0,
+            rotation_origin: Point::zero(),
+            bold: false,
+            oblique: 0,
+            effect: TextEffect::None,
+            letter_spacing: 0,
+            word_spacing: 0,
+            ligatures: true,
+            kerning: true,
+            figures: Figures::Proportional,
+        }
+    }
+
+    /// Creates a new text style with the given text color and charset.
+    pub fn with_charset(text_color: C, charset: &'static Charset<'static>) -> Self {
+        Self {
+            fallback_chain: FallbackChain::new(core::slice::from_ref(charset)),
+            ..Self::new(text_color)
+        }
+    }
+
+    /// Returns the charset, or the first charset of the fallback chain.
+    pub fn charset(&self) -> &'static Charset<'static> {
+        self.fallback_chain.primary()
+    }
+
+    /// Sets the charset.
+    pub fn set_charset(&mut self, charset: &'static Charset<'static>) {
+        self.fallback_chain = FallbackChain::new(core::slice::from_ref(charset));
+    }
+
+    /// Returns the fallback chain of charsets.
+    pub fn fallback_chain(&self) -> FallbackChain<'static> {
+        self.fallback_chain
+    }
+
+    /// Sets the fallback chain of charsets, that is used
+    /// to look up the characters missing from the first charset.
+    pub fn set_fallback_chain(&mut self, fallback_chain: FallbackChain<'static>) {
+        self.fallback_chain = fallback_chain;
+    }
+
+    /// Returns the integer scale factor.
+    pub fn scale(&self) -> u8 {
+        self.scale
+    }
+
+    /// Sets the integer scale factor, that keeps the pixel art look on high resolution displays.
+    ///
+    /// Every pixel of a glyph, as well as the side bearings, kerning and line height,
+    /// are multiplied by the scale. A scale of 0 is treated as 1.
+    pub fn set_scale(&mut self, scale: u8) {
+        self.scale = scale.max(1);
+    }
+
+    /// Returns the rotation.
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    /// Returns the point that the text is rotated around.
+    pub fn rotation_origin(&self) -> Point {
+        self.rotation_origin
+    }
+
+    /// Sets the clockwise rotation of the text around the origin.
+    ///
+    /// The text is laid out horizontally, and then the glyphs, the background,
+    /// the decorations and the bounding box returned by `measure_string` are rotated around
+    /// the origin. The positions passed to and returned from the renderer stay unrotated,
+    /// so the lines of a multiline [`Text`](embedded_graphics::text::Text) are rotated together.
+    /// Pass the position of the text as the origin to rotate it in place.
+    ///
+    /// To rotate a `TextBox`, draw it into a [`RotatedDrawTarget`](crate::RotatedDrawTarget).
+    pub fn set_rotation(&mut self, rotation: Rotation, origin: Point) {
+        self.rotation = rotation;
+        self.rotation_origin = origin;
+    }
+
+    /// Returns true if the text is bold.
+    pub fn is_bold(&self) -> bool {
+        self.bold
+    }
+
+    /// Sets the synthetic bold, that smears every glyph one pixel to the right.
+    ///
+    /// Every glyph becomes one pixel wider, so the spacing between the glyphs is preserved.
+    pub fn set_bold(&mut self, bold: bool) {
+        self.bold = bold;
+    }
+
+    /// Returns the number of rows per one pixel of the oblique shear, or 0 for the upright text.
+    pub fn oblique(&self) -> u8 {
+        self.oblique
+    }
+
+    /// Sets the oblique text, that shears the glyph rows one pixel to the right
+    /// every `rows` rows above the baseline. Set to 0 for the upright text.
+    ///
+    /// The glyphs stick out of their cells, which is included in the bounding box,
+    /// but doesn't change the spacing between the glyphs.
+    pub fn set_oblique(&mut self, rows: u8) {
+        self.oblique = rows;
+    }
+
+    /// Returns the text effect.
+    pub fn effect(&self) -> TextEffect<C>
+    where
+        C: Copy,
+    {
+        self.effect
+    }
+
+    /// Sets the text effect, that is drawn under the text.
+    ///
+    /// The effect is included in the bounding box,
+    /// but doesn't change the spacing between the glyphs.
+    pub fn set_effect(&mut self, effect: TextEffect<C>) {
+        self.effect = effect;
+    }
+
+    /// Returns the letter spacing.
+    pub fn letter_spacing(&self) -> i8 {
+        self.letter_spacing
+    }
+
+    /// Sets the letter spacing, that is added after every character,
+    /// to loosen the text with positive values or tighten it with negative values.
+    pub fn set_letter_spacing(&mut self, letter_spacing: i8) {
+        self.letter_spacing = letter_spacing;
+    }
+
+    /// Returns the word spacing.
+    pub fn word_spacing(&self) -> i8 {
+        self.word_spacing
+    }
+
+    /// Sets the word spacing, that is added after every space character,
+    /// in addition to the letter spacing.
+    pub fn set_word_spacing(&mut self, word_spacing: i8) {
+        self.word_spacing = word_spacing;
+    }
+
+    /// Returns true if the ligatures are enabled.
+    pub fn ligatures(&self) -> bool {
+        self.ligatures
+    }
+
+    /// Enables or disables the ligatures, e.g. "fi" or "jj", that are enabled by default.
+    ///
+    /// Without the ligatures every character is drawn with its own glyph,
+    /// which is useful for editable text, where the caret can be between any two characters.
+    pub fn set_ligatures(&mut self, ligatures: bool) {
+        self.ligatures = ligatures;
+    }
+
+    /// Returns true if the kerning is enabled.
+    pub fn kerning(&self) -> bool {
+        self.kerning
+    }
+
+    /// Enables or disables the kerning between glyph pairs, that is enabled by default.
+    ///
+    /// The side bearings are still applied when the kerning is disabled.
+    pub fn set_kerning(&mut self, kerning: bool) {
+        self.kerning = kerning;
+    }
+
+    /// Returns the spacing of the digits.
+    pub fn figures(&self) -> Figures {
+        self.figures
+    }
+
+    /// Sets the spacing of the digits.
+    ///
+    /// Use [`Figures::Tabular`] for counters, clocks and sensor readouts,
+    /// that shouldn't jitter when the value changes.
+    pub fn set_figures(&mut self, figures: Figures) {
+        self.figures = figures;
+    }
+
+    /// Returns an iterator over the positioned glyphs in a line of text.
+    ///
+    /// The positions are in pixels relative to the top left corner of the line,
+    /// they are scaled, but not rotated.
+    pub fn layout<'t>(&self, text: &'t str) -> impl Iterator<Item = PositionedGlyph> + 't {
+        self.layout_texts(core::iter::once((text, self.bold)))
+            .map(|(_, glyph)| glyph)
+    }
+
+    /// Returns an iterator over the positioned glyphs in several texts laid out
+    /// as one continuous line, together with the indices of the texts they belong to.
+    ///
+    /// The byte ranges are relative to the start of each text.
+    /// Every text comes with a flag, that makes it bold.
+    fn layout_texts<'t, I>(&self, texts: I) -> impl Iterator<Item = (usize, PositionedGlyph)> + 't
+    where
+        I: Iterator<Item = (&'t str, bool)> + Clone + 't,
+    {
+        let scale = self.scale as i32;
+        let mut line_glyphs = self
+            .run_glyphs(self.line_layout(), texts.clone())
+            .peekable();
+        let mut texts = texts.map(|(text, _)| text).enumerate();
+        let mut text = None;
+        core::iter::from_fn(move || {
+            let (index, line_glyph) = line_glyphs.next()?;
+            // The runs without glyphs are skipped.
+            while text.is_none_or(|(i, _)| i != index) {
+                text = texts.next();
+            }
+            let end = match line_glyphs.peek() {
+                Some((next_index, next)) if *next_index == index => next.byte_offset,
+                _ => text.map_or(0, |(_, text)| text.len()),
+            };
+            let glyph = PositionedGlyph {
+                charset: line_glyph.glyph.charset,
+                index: line_glyph.glyph.index.0,
+                bytes: line_glyph.byte_offset..end,
+                position: Point::new(line_glyph.offset, line_glyph.glyph.top) * scale,
+                advance: line_glyph.advance * scale,
+                area: line_glyph.glyph.area(),
+            };
+            Some((index, glyph))
+        })
+    }
+
+    /// Returns the byte offset of the caret position nearest to the horizontal offset
+    /// in pixels from the text position.
+    ///
+    /// The caret positions are between the characters, the ligatures are split evenly
+    /// between the characters that they are drawn for.
+    pub fn hit_test(&self, text: &str, x: i32) -> usize {
+        let mut nearest = (0, i32::MAX);
+        for (byte_offset, caret_x) in self.caret_positions(text) {
+            let distance = (caret_x - x).abs();
+            if distance < nearest.1 {
+                nearest = (byte_offset, distance);
+            }
+        }
+        nearest.0
+    }
+
+    /// Returns the horizontal offset in pixels from the text position
+    /// of the caret before the character at the byte offset.
+    ///
+    /// The caret is in the first column after the previous glyph, at the start of the text
+    /// it is at the text position. The byte offsets inside a character or past the end of
+    /// the text are rounded down to the start of the character or the end of the text.
+    pub fn caret_x(&self, text: &str, byte_offset: usize) -> i32 {
+        self.caret_positions(text)
+            .take_while(|&(caret_offset, _)| caret_offset <= byte_offset)
+            .last()
+            .map_or(0, |(_, caret_x)| caret_x)
+    }
+
+    /// Returns an iterator over the byte offsets of the caret positions in a line of text,
+    /// together with their horizontal offsets in pixels.
+    fn caret_positions<'t>(&self, text: &'t str) -> impl Iterator<Item = (usize, i32)> + 't {
+        let mut glyph_end = 0;
+        self.layout(text)
+            .flat_map(move |glyph| {
+                let bytes = glyph.bytes();
+                let char_count = text[bytes.clone()].chars().count() as i32;
+                let (left, advance) = (glyph.position().x, glyph.advance());
+                let prev_glyph_end = core::mem::replace(&mut glyph_end, left + advance);
+                text[bytes.clone()]
+                    .char_indices()
+                    .enumerate()
+                    .map(move |(i, (char_offset, _))| {
+                        let caret_x = match i as i32 {
+                            0 => prev_glyph_end,
+                            i => left + advance * i / char_count,
+                        };
+                        (bytes.start + char_offset, caret_x)
+                    })
+            })
+            .chain(core::iter::once((
+                text.len(),
+                self.advance_position(text, 0),
+            )))
+    }
+
+    /// Returns the longest prefix of a line of text, that fits into the maximum width in pixels
+    /// together with the ellipsis, and true if the text was truncated.
+    ///
+    /// The text is returned as is if it fits. The prefix ends at a glyph boundary,
+    /// without the trailing whitespace.
+    pub fn truncate<'t>(&self, text: &'t str, max_width: u32) -> (&'t str, bool) {
+        let max_width = max_width as i32 - self.trailing_overhang();
+        if text.is_empty() || self.advance_position(text, 0) <= max_width {
+            return (text, false);
+        }
+        let max_width = max_width - self.advance_position(self.ellipsis(), 0);
+        let end = self
+            .layout(text)
+            .take_while(|glyph| glyph.position().x + glyph.advance() <= max_width)
+            .last()
+            .map_or(0, |glyph| glyph.bytes().end);
+        (text[..end].trim_end(), true)
+    }
+
+    /// Returns the longest prefix and suffix of a line of text, that fit into the maximum width
+    /// in pixels together with the ellipsis between them, and true if the text was truncated.
+    ///
+    /// The text is returned as the prefix if it fits. The suffix takes up to a half
+    /// of the available width, which keeps the file names visible in the long paths.
+    pub fn truncate_middle<'t>(&self, text: &'t str, max_width: u32) -> (&'t str, &'t str, bool) {
+        let max_width = max_width as i32 - self.trailing_overhang();
+        if text.is_empty() || self.advance_position(text, 0) <= max_width {
+            return (text, "", false);
+        }
+        let max_width = max_width - self.advance_position(self.ellipsis(), 0);
+        let start = text
+            .char_indices()
+            .rev()
+            .map(|(start, _)| start)
+            .take_while(|&start| self.advance_position(&text[start..], 0) <= max_width / 2)
+            .last()
+            .unwrap_or(text.len());
+        let suffix = text[start..].trim_start();
+        let max_width = max_width - self.advance_position(suffix, 0);
+        let end = self
+            .layout(text)
+            .take_while(|glyph| glyph.position().x + glyph.advance() <= max_width)
+            .last()
+            .map_or(0, |glyph| glyph.bytes().end);
+        (text[..end].trim_end(), suffix, true)
+    }
+
+    /// Returns how far in pixels the last glyph of a line sticks out after its advance,
+    /// because of the oblique overhang and the text effect.
+    pub(crate) fn trailing_overhang(&self) -> i32 {
+        let (_, overhang_right) = self.overhang();
+        let (_, effect_bottom_right) = self.effect.extent();
+        self.scaled(Point::new(overhang_right + effect_bottom_right.x, 0))
+            .x
+    }
+
+    /// Returns the ellipsis character, or three dots if the charsets don't have it.
+    fn ellipsis(&self) -> &'static str {
+        let charsets = self.fallback_chain.charsets();
+        match charsets.iter().any(|charset| charset.contains('…')) {
+            true => "…",
+            false => "...",
+        }
+    }
+
+    /// Draws the text using the binary color format.
+    fn draw_string_binary<D>(
+        &self,
+        text: &str,
+        position: Point,
+        mut target: D,
+    ) -> Result<Point, D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        let mut x = 0;
+        for line_glyph in self.line_glyphs(text) {
+            self.draw_glyph_binary(
+                line_glyph,
+                position + self.scaled(Point::new(line_glyph.offset, 0)),
+                &mut target,
+            )?;
+            x = line_glyph.offset + line_glyph.advance;
+        }
+        Ok(position + self.scaled(Point::new(x, 0)))
+    }
+
+    /// Draws a glyph with the left edge at the position.
+    fn draw_glyph_binary<D>(
+        &self,
+        line_glyph: LineGlyph,
+        position: Point,
+        target: &mut D,
+    ) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        let glyph = line_glyph.glyph;
+        if self.scale == 1 && !line_glyph.bold && self.oblique == 0 {
+            let origin = position + Point::new(0, glyph.top);
+            return glyph
+                .charset
+                .image
+                .draw_sub_image(&mut target.translated(origin), &glyph.area());
+        }
+
+        let (overhang_left, overhang_right) = self.overhang();
+        let area = Rectangle::new(
+            Point::new(-overhang_left, glyph.top),
+            Size::new(
+                (overhang_left + line_glyph.width() + overhang_right) as u32,
+                glyph.area().size.height,
+            ),
+        );
+        self.fill_mask(area, position, target, |point| {
+            self.is_ink(line_glyph, point)
+        })
+    }
+
+    /// Draws the text effect of a line of text using the binary color format.
+    fn draw_effect_binary<D>(
+        &self,
+        glyphs: impl Iterator<Item = LineGlyph>,
+        position: Point,
+        mut target: D,
+    ) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        let offsets = self.effect.offsets();
+        let (overhang_left, overhang_right) = self.overhang();
+        for line_glyph in glyphs {
+            let glyph = line_glyph.glyph;
+            // The effect offsets are at most one pixel in every direction.
+            let area = Rectangle::new(
+                Point::new(-overhang_left - 1, glyph.top - 1),
+                Size::new(
+                    (overhang_left + line_glyph.width() + overhang_right + 2) as u32,
+                    glyph.area().size.height + 2,
+                ),
+            );
+            self.fill_mask(
+                area,
+                position + self.scaled(Point::new(line_glyph.offset, 0)),
+                &mut target,
+                |point| {
+                    offsets
+                        .iter()
+                        .any(|&offset| self.is_ink(line_glyph, point - offset))
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Fills the runs of the set pixels in every row of the area with scaled blocks.
+    ///
+    /// The area and the pixels are in font units relative to the position.
+    fn fill_mask<D>(
+        &self,
+        area: Rectangle,
+        position: Point,
+        target: &mut D,
+        is_set: impl Fn(Point) -> bool,
+    ) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        let left = area.top_left.x;
+        let right = left + area.size.width as i32;
+        for y in area.rows() {
+            let mut x = left;
+            while x < right {
+                let start = x;
+                while x < right && is_set(Point::new(x, y)) {
+                    x += 1;
+                }
+                if x > start {
+                    let run =
+                        Rectangle::new(Point::new(start, y), Size::new((x - start) as u32, 1));
+                    target.fill_solid(&self.scaled_rectangle(position, run), BinaryColor::On)?;
+                } else {
+                    x += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns true if the glyph, as it is drawn, has a pixel set at the given point,
+    /// relative to the left edge of the glyph and the top of the line.
+    fn is_ink(&self, line_glyph: LineGlyph, point: Point) -> bool {
+        let (glyph, bold) = (line_glyph.glyph, line_glyph.bold);
+        let point = point - Point::new(self.oblique_shift(point.y), 0);
+        glyph.is_ink(point) || (bold && glyph.is_ink(point - Point::new(1, 0)))
+    }
+
+    /// Returns the x position after the last character in the line of text.
+    pub(crate) fn advance_position(&self, text: &str, x: i32) -> i32 {
+        x + self.scaled(Point::new(self.horizontal_extent(text).1, 0)).x
+    }
+
+    /// Returns the left and the right edges of a line of text in font units.
+    ///
+    /// The left edge can be to the left of the text position,
+    /// when the first character has a negative left side bearing,
+    /// e.g. letter 'j'. The right edge includes the letter and word spacing
+    /// after the last character.
+    fn horizontal_extent(&self, text: &str) -> (i32, i32) {
+        glyphs_extent(self.line_glyphs(text), 0)
+    }
+
+    /// Returns the start and the end of every text laid out as one continuous line,
+    /// in font units.
+    ///
+    /// A text starts where the previous one ends, and the first text starts where
+    /// the layout ends.
+    fn run_ranges<'t, I>(
+        &self,
+        layout: LineLayout,
+        texts: I,
+    ) -> impl Iterator<Item = (i32, i32)> + 't
+    where
+        I: IntoIterator<Item = (&'t str, bool)>,
+        I::IntoIter: Clone + 't,
+    {
+        let texts = texts.into_iter();
+        let mut glyphs = self.run_glyphs(layout, texts.clone()).peekable();
+        let mut x = layout.x;
+        (0..texts.count()).map(move |index| {
+            let start = x;
+            while let Some((_, line_glyph)) = glyphs.next_if(|&(i, _)| i == index) {
+                x = line_glyph.offset + line_glyph.advance;
+            }
+            (start, x)
+        })
+    }
+
+    /// Returns the metrics of a line of text with the given horizontal extent in font units.
+    ///
+    /// The bounding box includes the oblique overhang and the text effect,
+    /// if the line is not empty.
+    fn line_metrics(
+        &self,
+        (left, right): (i32, i32),
+        is_empty: bool,
+        position: Point,
+        baseline: Baseline,
+    ) -> TextMetrics {
+        // The oblique glyphs and the text effect can stick out of the cells.
+        let (effect_top_left, effect_bottom_right) = match is_empty {
+            true => (Point::zero(), Point::zero()),
+            false => self.effect.extent(),
+        };
+        let (overhang_left, overhang_right) = match is_empty {
+            true => (0, 0),
+            false => self.overhang(),
+        };
+        let top_left = self.scaled(Point::new(
+            left - overhang_left - effect_top_left.x,
+            effect_top_left.y,
+        ));
+        let bottom_right = self.scaled(Point::new(
+            right + overhang_right + effect_bottom_right.x,
+            effect_bottom_right.y,
+        ));
+
+        let bb_position =
+            position + Point::new(top_left.x, -self.baseline_offset(baseline) - top_left.y);
+        let bb_width = bottom_right.x - top_left.x;
+        let bb_height = self.line_height_px() as i32 + top_left.y + bottom_right.y;
+        let bb_size = Size::new(bb_width.max(0) as u32, bb_height as u32);
+
+        let bounding_box = Rectangle::new(bb_position, bb_size);
+        let next_position = position + self.scaled(Point::new(right, 0));
+        TextMetrics {
+            bounding_box: self
+                .rotation
+                .rotate_rectangle(bounding_box, self.rotation_origin),
+            next_position,
+        }
+    }
+
+    /// Returns the smallest spacing between two glyphs in font units,
+    /// that includes the tabular digits, or less.
+    fn min_spacing(&self) -> i32 {
+        let charsets = self.fallback_chain.charsets().iter();
+        let (left, right) = charsets.clone().map(Charset::min_side_bearings).fold(
+            (0, 0),
+            |(left, right), (left_bearing, right_bearing)| {
+                (left.min(left_bearing), right.min(right_bearing))
+            },
+        );
+        left + right + charsets.map(Charset::min_kerning).fold(0, i32::min)
+    }
+
+    /// Returns how far the oblique glyphs stick out to the left and to the right of their cells.
+    fn overhang(&self) -> (i32, i32) {
+        let line_height = self.charset().line_height as i32;
+        (-self.oblique_shift(line_height - 1), self.oblique_shift(0))
+    }
+
+    /// Returns the horizontal shift of the oblique glyph row, relative to the top of the line.
+    ///
+    /// The rows above the baseline are shifted to the right, and the rows below it to the left.
+    fn oblique_shift(&self, y: i32) -> i32 {
+        match self.oblique {
+            0 => 0,
+            rows => (self.charset().baseline as i32 - 1 - y).div_euclid(rows as i32),
+        }
+    }
+
+    /// Returns an iterator over the glyphs in a line of text, together with their
+    /// positions and advances.
+    fn line_glyphs<'t>(&self, text: &'t str) -> impl Iterator<Item = LineGlyph> + 't {
+        self.run_glyphs(self.line_layout(), core::iter::once((text, self.bold)))
+            .map(|(_, line_glyph)| line_glyph)
+    }
+
+    /// Returns the state of laying out a new line.
+    pub(crate) fn line_layout(&self) -> LineLayout {
+        LineLayout {
+            fallback_chain: self.fallback_chain,
+            letter_spacing: self.letter_spacing as i32,
+            word_spacing: self.word_spacing as i32,
+            kerning: self.kerning,
+            figures: self.figures,
+            x: 0,
+            prev_glyph: None,
+            prev_tabular: false,
+        }
+    }
+
+    /// Returns an iterator over the glyphs in several texts laid out as one continuous line,
+    /// that continues the layout, together with the indices of the texts they belong to.
+    ///
+    /// The spacing and kerning are applied across the text boundaries,
+    /// and the byte offsets are relative to the start of each text.
+    /// Every text comes with a flag, that makes it bold.
+    fn run_glyphs<'t, I>(
+        &self,
+        mut layout: LineLayout,
+        texts: I,
+    ) -> impl Iterator<Item = (usize, LineGlyph)> + 't
+    where
+        I: IntoIterator<Item = (&'t str, bool)>,
+        I::IntoIter: 't,
+    {
+        let fallback_chain = self.fallback_chain;
+        let ligatures = self.ligatures;
+        texts
+            .into_iter()
+            .enumerate()
+            .flat_map(move |(index, (text, bold))| {
+                fallback_chain
+                    .glyphs(text, ligatures)
+                    .map(move |(byte_offset, glyph)| (index, text, bold, byte_offset, glyph))
+            })
+            .map(move |(index, text, bold, byte_offset, glyph)| {
+                (index, layout.place(text, byte_offset, bold, glyph))
+            })
+    }
+
+    /// Multiplies a point in font units by the scale.
+    fn scaled(&self, point: Point) -> Point {
+        point * self.scale as i32
+    }
+
+    /// Returns a rectangle in font units relative to the position, multiplied by the scale.
+    fn scaled_rectangle(&self, position: Point, rectangle: Rectangle) -> Rectangle {
+        Rectangle::new(
+            position + self.scaled(rectangle.top_left),
+            rectangle.size * self.scale as u32,
+        )
+    }
+
+    /// Returns a horizontal line in font units relative to the position, multiplied by the scale.
+    fn scaled_line(&self, position: Point, y: i32, start: i32, end: i32) -> Rectangle {
+        self.scaled_rectangle(
+            position,
+            Rectangle::new(Point::new(start, y), Size::new((end - start) as u32, 1)),
+        )
+    }
+
+    /// Draws the underline using the binary color format.
+    fn draw_underline_binary<D>(
+        &self,
+        glyphs: impl Iterator<Item = LineGlyph>,
+        position: Point,
+        mut target: D,
+    ) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = BinaryColor>,
+    {
+        self.underline_segments(glyphs, |start, end| {
+            target.fill_solid(&self.underline(position, start, end), BinaryColor::On)
+        })
+    }
+
+    /// Returns the scaled underline between the start and the end in font units.
+    fn underline(&self, position: Point, start: i32, end: i32) -> Rectangle {
+        let charset = self.charset();
+        let y = (charset.baseline + charset.underline_offset) as i32;
+        self.scaled_line(position, y, start, end)
+    }
+
+    /// Calls the fill with the start and the end of every segment of the underline.
+    ///
+    /// The line is interrupted around the glyph pixels that cross it,
+    /// e.g. the descenders of 'g', 'j' and 'y', to keep the text legible.
+    fn underline_segments<E>(
+        &self,
+        glyphs: impl Iterator<Item = LineGlyph>,
+        mut fill: impl FnMut(i32, i32) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let charset = self.charset();
+        let y = (charset.baseline + charset.underline_offset) as i32;
+        // Glyphs only overlap their immediate neighbours,
+        // so it is enough to look at the previous and the next glyph.
+        let has_ink = |glyph: Option<LineGlyph>, x: i32| {
+            glyph.is_some_and(|glyph| {
+                (x - 1..=x + 1).any(|x| self.is_ink(glyph, Point::new(x - glyph.offset, y)))
+            })
+        };
+        let mut fill = |start: i32, end: i32| match end > start {
+            true => fill(start, end),
+            false => Ok(()),
+        };
+
+        let mut glyphs = glyphs.peekable();
+        let Some(first) = glyphs.peek() else {
+            return Ok(());
+        };
+        let mut prev_glyph = None;
+        let mut line_start = first.offset;
+        let mut x = line_start;
+        let mut width = line_start;
+        while let Some(glyph) = glyphs.next() {
+            let next_glyph = glyphs.peek().copied();
+            width = glyph.offset + glyph.advance;
+            let cell_end = next_glyph.map_or(width, |next| next.offset);
+            while x < cell_end {
+                if has_ink(prev_glyph, x) || has_ink(Some(glyph), x) || has_ink(next_glyph, x) {
+                    fill(line_start, x)?;
+                    line_start = x + 1;
+                }
+                x += 1;
+            }
+            prev_glyph = Some(glyph);
+        }
+        fill(line_start, width)
+    }
+
+    /// Returns the vertical offset between the line position and the top edge of the bounding box.
+    fn baseline_offset(&self, baseline: Baseline) -> i32 {
+        let line_height = self.line_height_px() as i32;
+        match baseline {
+            Baseline::Top => 0,
+            Baseline::Bottom => line_height - 1,
+            Baseline::Middle => (line_height - 1) / 2,
+            Baseline::Alphabetic => self.scaled(Point::new(0, self.charset().baseline as i32)).y,
+        }
+    }
+
+    /// Returns the line height in pixels.
+    fn line_height_px(&self) -> u32 {
+        self.charset().line_height * self.scale as u32
+    }
+}
+
+impl<C> TextStyle<C>
+where
+    C: PixelColor,
+{
+    /// Draws a line of text truncated with the ellipsis to the maximum width in pixels,
+    /// and returns the position after the drawn text.
+    ///
+    /// The ellipsis is only drawn when it fits into the maximum width,
+    /// so the text never overflows it.
+    pub fn draw_truncated<D>(
+        &self,
+        text: &str,
+        position: Point,
+        baseline: Baseline,
+        max_width: u32,
+        truncation: Truncation,
+        target: &mut D,
+    ) -> Result<Point, D::Error>
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let ellipsis = self.ellipsis();
+        let (prefix, suffix, truncated) = match truncation {
+            Truncation::End => {
+                let (prefix, truncated) = self.truncate(text, max_width);
+                (prefix, "", truncated)
+            }
+            Truncation::Middle => self.truncate_middle(text, max_width),
+        };
+        let mut position = self.draw_string(prefix, position, baseline, target)?;
+        let ellipsis_width = self.advance_position(ellipsis, 0) + self.trailing_overhang();
+        if truncated && ellipsis_width <= max_width as i32 {
+            position = self.draw_string(ellipsis, position, baseline, target)?;
+        }
+        self.draw_string(suffix, position, baseline, target)
+    }
+
+    /// Draws a line of rich text, that consists of spans with their own colors,
+    /// and returns the position after the drawn text.
+    ///
+    /// The spans are laid out as one continuous line, so the side bearings and kerning
+    /// are applied across the span boundaries.
+    pub fn draw_spans<D>(
+        &self,
+        spans: &[(&str, SpanStyle<C>)],
+        position: Point,
+        baseline: Baseline,
+        target: &mut D,
+    ) -> Result<Point, D::Error>
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let layout = &mut self.line_layout();
+        self.draw_runs(spans.iter().copied(), layout, position, baseline, target)
+    }
+
+    /// Measures a line of rich text, that consists of spans, as it is drawn by
+    /// [`draw_spans`](Self::draw_spans).
+    pub fn measure_spans(
+        &self,
+        spans: &[(&str, SpanStyle<C>)],
+        position: Point,
+        baseline: Baseline,
+    ) -> TextMetrics {
+        self.measure_runs(spans.iter().copied(), position, baseline)
+    }
+
+    /// Draws the glyphs of a line of text, that intersect the horizontal range in pixels
+    /// relative to the top left position, together with their effect and decorations.
+    ///
+    /// The text is not rotated, and the background is not drawn.
+    pub(crate) fn draw_visible<D>(
+        &self,
+        text: &str,
+        position: Point,
+        visible: Range<i32>,
+        target: &mut D,
+    ) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = C>,
+    {
+        // The glyphs are extended by the oblique overhang and the effect.
+        let (overhang_left, overhang_right) = self.overhang();
+        let (effect_top_left, effect_bottom_right) = self.effect.extent();
+        let glyphs = || {
+            self.line_glyphs(text).filter(|line_glyph| {
+                let left = line_glyph.offset - overhang_left - effect_top_left.x;
+                let right =
+                    line_glyph.offset + line_glyph.width() + overhang_right + effect_bottom_right.x;
+                self.scaled(Point::new(left, 0)).x < visible.end
+                    && self.scaled(Point::new(right, 0)).x > visible.start
+            })
+        };
+
+        if let Some(color) = self.effect.color() {
+            self.draw_effect_binary(glyphs(), position, MogeeFontDrawTarget::new(target, color))?;
+        }
+
+        if let Some(color) = self.text_color {
+            for line_glyph in glyphs() {
+                self.draw_glyph_binary(
+                    line_glyph,
+                    position + self.scaled(Point::new(line_glyph.offset, 0)),
+                    &mut MogeeFontDrawTarget::new(target, color),
+                )?;
+            }
+        }
+
+        if let Some(color) = self.decoration_color(self.underline_color) {
+            self.draw_underline_binary(
+                glyphs(),
+                position,
+                MogeeFontDrawTarget::new(target, color),
+            )?;
+        }
+
+        if let Some(color) = self.decoration_color(self.strikethrough_color) {
+            let (left, right) = glyphs_extent(glyphs(), 0);
+            let y = self.charset().strikethrough_offset() as i32;
+            target.fill_solid(&self.scaled_line(position, y, left, right), color)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the span style with the colors of this text style.
+    pub(crate) fn span_style(&self) -> SpanStyle<C> {
+        SpanStyle {
+            text_color: self.text_color,
+            background_color: self.background_color,
+            underline_color: self.underline_color,
+            strikethrough_color: self.strikethrough_color,
+            bold: false,
+        }
+    }
+
+    /// Returns the color of a text decoration.
+    fn decoration_color(&self, decoration_color: DecorationColor<C>) -> Option<C> {
+        self.span_style().decoration_color(decoration_color)
+    }
+
+    /// Draws the styled runs of text as one continuous line, that continues the layout,
+    /// and updates the layout to the end of the runs.
+    ///
+    /// The position is at the start of the line, and the returned position is after the runs.
+    pub(crate) fn draw_runs<'t, D, I>(
+        &self,
+        spans: I,
+        layout: &mut LineLayout,
+        position: Point,
+        baseline: Baseline,
+        target: &mut D,
+    ) -> Result<Point, D::Error>
+    where
+        D: DrawTarget<Color = C>,
+        I: Iterator<Item = (&'t str, SpanStyle<C>)> + Clone,
+    {
+        let target = &mut RotatedDrawTarget::new(target, self.rotation_origin, self.rotation);
+        let position = position - Point::new(0, self.baseline_offset(baseline));
+        let texts = || {
+            spans
+                .clone()
+                .map(|(text, span)| (text, self.bold || span.bold))
+        };
+        let start_layout = *layout;
+        let glyphs = || {
+            self.run_glyphs(start_layout, texts())
+                .map(|(_, line_glyph)| line_glyph)
+        };
+
+        // Lay out the runs, and draw all the backgrounds first, because the glyphs
+        // at the span boundaries might overlap the neighbouring spans.
+        let mut extent = None;
+        for (text, span) in spans.clone() {
+            let start = layout.x;
+            let bold = self.bold || span.bold;
+            for (byte_offset, glyph) in self.fallback_chain.glyphs(text, self.ligatures) {
+                let line_glyph = layout.place(text, byte_offset, bold, glyph);
+                let left = extent.map_or(line_glyph.offset, |(left, _)| left);
+                extent = Some((left, line_glyph.offset + line_glyph.advance));
+            }
+            if let Some(color) = span.background_color {
+                let size = Size::new((layout.x - start).max(0) as u32, self.charset().line_height);
+                let area = Rectangle::new(Point::new(start, 0), size);
+                target.fill_solid(&self.scaled_rectangle(position, area), color)?;
+            }
+        }
+        let (left, right) = extent.unwrap_or((start_layout.x, start_layout.x));
+
+        if let Some(color) = self.effect.color() {
+            self.draw_effect_binary(glyphs(), position, MogeeFontDrawTarget::new(target, color))?;
+        }
+
+        let mut run_glyphs = self.run_glyphs(start_layout, texts()).peekable();
+        for (index, (_, span)) in spans.clone().enumerate() {
+            while let Some((_, line_glyph)) = run_glyphs.next_if(|&(i, _)| i == index) {
+                if let Some(color) = span.text_color {
+                    self.draw_glyph_binary(
+                        line_glyph,
+                        position + self.scaled(Point::new(line_glyph.offset, 0)),
+                        &mut MogeeFontDrawTarget::new(target, color),
+                    )?;
+                }
+            }
+        }
+
+        let is_decorated = |span: &SpanStyle<C>| {
+            span.decoration_color(span.underline_color).is_some()
+                || span.decoration_color(span.strikethrough_color).is_some()
+        };
+        if spans.clone().any(|(_, span)| is_decorated(&span)) {
+            let y = self.charset().strikethrough_offset() as i32;
+            let strikethrough = |span: SpanStyle<C>, start, end| {
+                let color = span.decoration_color(span.strikethrough_color)?;
+                (end > start).then(|| (self.scaled_line(position, y, start, end), color))
+            };
+            // Like a line of text, the first span of a new line is decorated
+            // from its first glyph.
+            let mut ranges = spans
+                .clone()
+                .zip(self.run_ranges(start_layout, texts()))
+                .enumerate()
+                .map(|(index, ((_, span), (start, end)))| {
+                    match index == 0 && start_layout.prev_glyph.is_none() {
+                        true => (span, left, end),
+                        false => (span, start, end),
+                    }
+                })
+                .peekable();
+            // The underline segments and the span ranges both go from left to right,
+            // so every segment is split between the spans it overlaps.
+            self.underline_segments(glyphs(), |segment_start, segment_end| {
+                while let Some(&(span, start, end)) = ranges.peek() {
+                    if start >= segment_end {
+                        break;
+                    }
+                    let (clip_start, clip_end) = (segment_start.max(start), segment_end.min(end));
+                    if let (Some(color), true) = (
+                        span.decoration_color(span.underline_color),
+                        clip_end > clip_start,
+                    ) {
+                        target
+                            .fill_solid(&self.underline(position, clip_start, clip_end), color)?;
+                    }
+                    if end > segment_end {
+                        break;
+                    }
+                    if let Some((line, color)) = strikethrough(span, start, end) {
+                        target.fill_solid(&line, color)?;
+                    }
+                    ranges.next();
+                }
+                Ok(())
+            })?;
+            for (line, color) in
+                ranges.filter_map(|(span, start, end)| strikethrough(span, start, end))
+            {
+                target.fill_solid(&line, color)?;
+            }
+        }
+
+        let next_position = position
+            + Point::new(
+                self.scaled(Point::new(right, 0)).x,
+                self.baseline_offset(baseline),
+            );
+        Ok(next_position)
+    }
+
+    /// Draws the columns of pixels of the text, that continues the layout,
+    /// between the start and the end in font units, and returns the position after the text.
+    pub(crate) fn draw_columns<D>(
+        &self,
+        text: &str,
+        mut layout: LineLayout,
+        columns: Range<i32>,
+        position: Point,
+        baseline: Baseline,
+        target: &mut D,
+    ) -> Result<Point, D::Error>
+    where
+        D: DrawTarget<Color = C>,
+    {
+        let glyphs = || {
+            self.run_glyphs(layout, core::iter::once((text, self.bold)))
+                .map(|(_, line_glyph)| line_glyph)
+        };
+        let left = glyphs().map(|glyph| glyph.offset).fold(layout.x, i32::min);
+        let right = glyphs().fold(layout.x, |right, glyph| {
+            right.max(glyph.offset + glyph.advance.max(glyph.width()))
+        });
+        let (overhang_left, overhang_right) = self.overhang();
+        let (effect_top_left, effect_bottom_right) = self.effect.extent();
+        let start = columns.start.max(left - overhang_left - effect_top_left.x);
+        let end = columns
+            .end
+            .min(right + overhang_right + effect_bottom_right.x);
+
+        let area = Rectangle::new(
+            Point::new(start, -effect_top_left.y),
+            Size::new(
+                (end - start).max(0) as u32,
+                (self.charset().line_height as i32 + effect_top_left.y + effect_bottom_right.y)
+                    as u32,
+            ),
+        );
+        let top_left = position - Point::new(0, self.baseline_offset(baseline));
+        let clipping_area = self
+            .rotation
+            .rotate_rectangle(self.scaled_rectangle(top_left, area), self.rotation_origin);
+        self.draw_runs(
+            core::iter::once((text, self.span_style())),
+            &mut layout,
+            position,
+            baseline,
+            &mut target.clipped(&clipping_area),
+        )
+    }
+
+    /// Returns the column in font units, from which the text, that continues the layout,
+    /// changes when the next glyph is placed after it.
+    ///
+    /// The next glyph changes the underline of the last glyph, and it can also stick out
+    /// to the left of its cell as far as the smallest spacing between the glyphs allows,
+    /// together with its effect, that is drawn below the glyphs.
+    pub(crate) fn next_glyph_column(&self, text: &str, layout: LineLayout) -> i32 {
+        let (overhang_left, _) = self.overhang();
+        let (effect_top_left, _) = self.effect.extent();
+        let next_offset = |x| x + self.min_spacing() - overhang_left - effect_top_left.x;
+        self.run_glyphs(layout, core::iter::once((text, self.bold)))
+            .last()
+            .map_or(next_offset(layout.x), |(_, last)| {
+                last.offset.min(next_offset(last.offset + last.advance))
+            })
+    }
+
+    /// Continues the layout with the glyphs of the text, that are completely drawn
+    /// before the column in font units, except for the last of them,
+    /// and returns the byte offset of the first glyph, that is left.
+    ///
+    /// The last drawn glyph is left, because the underline of the next glyph depends on it.
+    pub(crate) fn skip_glyphs(&self, layout: &mut LineLayout, text: &str, column: i32) -> usize {
+        let (_, overhang_right) = self.overhang();
+        let (_, effect_bottom_right) = self.effect.extent();
+        let is_drawn = |glyph: &LineGlyph| {
+            glyph.offset + glyph.advance.max(glyph.width()) + overhang_right + effect_bottom_right.x
+                <= column
+        };
+        let glyphs = || {
+            self.run_glyphs(*layout, core::iter::once((text, self.bold)))
+                .map(|(_, line_glyph)| line_glyph)
+        };
+        let skipped = glyphs().take_while(is_drawn).count().saturating_sub(1);
+        let mut glyphs = self.fallback_chain.glyphs(text, self.ligatures);
+        for (byte_offset, glyph) in glyphs.by_ref().take(skipped) {
+            layout.place(text, byte_offset, self.bold, glyph);
+        }
+        glyphs
+            .next()
+            .map_or(text.len(), |(byte_offset, _)| byte_offset)
+    }
+
+    /// Returns an iterator over the positioned glyphs in the styled runs of text
+    /// laid out as one continuous line, together with the indices of the runs they belong to.
+    ///
+    /// The byte ranges are relative to the start of each run.
+    pub(crate) fn layout_runs<'t, I>(
+        &self,
+        spans: I,
+    ) -> impl Iterator<Item = (usize, PositionedGlyph)> + 't
+    where
+        I: Iterator<Item = (&'t str, SpanStyle<C>)> + Clone + 't,
+    {
+        let bold = self.bold;
+        self.layout_texts(spans.map(move |(text, span)| (text, bold || span.bold)))
+    }
+
+    /// Measures the styled runs of text as one continuous line.
+    pub(crate) fn measure_runs<'t, I>(
+        &self,
+        spans: I,
+        position: Point,
+        baseline: Baseline,
+    ) -> TextMetrics
+    where
+        I: Iterator<Item = (&'t str, SpanStyle<C>)> + Clone,
+    {
+        let texts = spans
+            .clone()
+            .map(|(text, span)| (text, self.bold || span.bold));
+        let glyphs = self.run_glyphs(self.line_layout(), texts);
+        let extent = glyphs_extent(glyphs.map(|(_, line_glyph)| line_glyph), 0);
+        let is_empty = spans.clone().all(|(text, _)| text.is_empty());
+        self.line_metrics(extent, is_empty, position, baseline)
+    }
+}
+
+/// Returns the left and the right edges of the glyphs in a line in font units,
+/// or the start of the line if there are no glyphs.
+fn glyphs_extent(mut glyphs: impl Iterator<Item = LineGlyph>, start: i32) -> (i32, i32) {
+    let Some(first) = glyphs.next() else {
+        return (start, start);
+    };
+    let last = glyphs.last().unwrap_or(first);
+    (first.offset, last.offset + last.advance)
+}
+
+/// Where the text is truncated with the ellipsis.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Truncation {
+    /// Truncate the end of the text.
+    #[default]
+    End,
+    /// Truncate the middle of the text, e.g. for file paths.
+    Middle,
+}
+
+impl<C> TextRenderer for TextStyle<C>
+where
+    C: PixelColor,
+{
+    type Color = C;
+
+    fn draw_string<D>(
+        &self,
+        text: &str,
+        position: Point,
+        baseline: Baseline,
+        target: &mut D,
+    ) -> Result<Point, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        // Draw the text horizontally, and rotate the pixels around the rotation origin.
+        let target = &mut RotatedDrawTarget::new(target, self.rotation_origin, self.rotation);
+        let position = position - Point::new(0, self.baseline_offset(baseline));
+
+        // Avoid measuring the text twice if the background color is not set.
+        let mut offset = None;
+
+        // We have to draw the background first, because if a glyph has a negative
+        // left side bearing or kerning, it might overlap the previous glyph.
+        if let Some(color) = self.background_color {
+            let bg_width = self.advance_position(text, 0);
+            let size = Size::new(bg_width.max(0) as u32, self.line_height_px());
+            target.fill_solid(&Rectangle::new(position, size), color)?;
+            offset = Some(bg_width);
+        }
+
+        // Draw the text effect under the text.
+        if let Some(color) = self.effect.color() {
+            self.draw_effect_binary(
+                self.line_glyphs(text),
+                position,
+                MogeeFontDrawTarget::new(target, color),
+            )?;
+        }
+
+        // Draw the text.
+        if let Some(color) = self.text_color {
+            let pos =
+                self.draw_string_binary(text, position, MogeeFontDrawTarget::new(target, color))?;
+            offset = Some(pos.x - position.x);
+        };
+
+        // Draw the underline.
+        if let Some(color) = self.decoration_color(self.underline_color) {
+            self.draw_underline_binary(
+                self.line_glyphs(text),
+                position,
+                MogeeFontDrawTarget::new(target, color),
+            )?;
+        }
+
+        // Draw the strikethrough across the whole bounding box.
+        if let Some(color) = self.decoration_color(self.strikethrough_color) {
+            let (left, right) = self.horizontal_extent(text);
+            let y = self.charset().strikethrough_offset() as i32;
+            target.fill_solid(&self.scaled_line(position, y, left, right), color)?;
+        }
+
+        let next_position = position
+            + Point::new(
+                offset.unwrap_or_else(|| self.advance_position(text, 0)),
+                self.baseline_offset(baseline),
+            );
+        Ok(next_position)
+    }
+
+    fn draw_whitespace<D>(
+        &self,
+        width: u32,
+        position: Point,
+        baseline: Baseline,
+        target: &mut D,
+    ) -> Result<Point, D::Error>
+    where
+        D: DrawTarget<Color = Self::Color>,
+    {
+        let target = &mut RotatedDrawTarget::new(target, self.rotation_origin, self.rotation);
+        // The width of the whitespace is measured with `measure_string`,
+        // so it already includes the letter and word spacing.
+        let position = position - Point::new(0, self.baseline_offset(baseline));
+        let charset = self.charset();
+        if let Some(color) = self.background_color {
+            let size = Size::new(width, self.line_height_px());
+            target.fill_solid(&Rectangle::new(position, size), color)?;
+        }
+        // The width is already in pixels, only the line offset and thickness are scaled.
+        let line = |y: u32| {
+            let line = self.scaled_line(position, y as i32, 0, 1);
+            Rectangle::new(line.top_left, Size::new(width, line.size.height))
+        };
+        if let Some(color) = self.decoration_color(self.underline_color) {
+            target.fill_solid(&line(charset.baseline + charset.underline_offset), color)?;
+        }
+        if let Some(color) = self.decoration_color(self.strikethrough_color) {
+            target.fill_solid(&line(charset.strikethrough_offset()), color)?;
+        }
+        let next_position = position + Point::new(width as i32, self.baseline_offset(baseline));
+        Ok(next_position)
+    }
+
+    fn measure_string(&self, text: &str, position: Point, baseline: Baseline) -> TextMetrics {
+        let extent = self.horizontal_extent(text);
+        self.line_metrics(extent, text.is_empty(), position, baseline)
+    }
+
+    fn line_height(&self) -> u32 {
+        self.line_height_px()
+    }
+}
+
+impl<C> CharacterStyle for TextStyle<C>
+where
+    C: PixelColor,
+{
+    type Color = C;
+
+    /// Sets the text color.
+    fn set_text_color(&mut self, text_color: Option<Self::Color>) {
+        self.text_color = text_color;
+    }
+
+    /// Sets the background color.
+    fn set_background_color(&mut self, background_color: Option<Self::Color>) {
+        self.background_color = background_color;
+    }
+
+    /// Sets the underline color.
+    fn set_underline_color(&mut self, underline_color: DecorationColor<Self::Color>) {
+        self.underline_color = underline_color;
+    }
+
+    /// Sets the strikethrough color.
+    fn set_strikethrough_color(&mut self, strikethrough_color: DecorationColor<Self::Color>) {
+        self.strikethrough_color = strikethrough_color;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics::{geometry::Size, mock_display::MockDisplay, primitives::PointsIter};
+    const TEXT_POS: Point = Point::new(4, 6);
+
+    /// Returns the width of a line of text in pixels.
+    fn width(style: &TextStyle<BinaryColor>, text: &str) -> i32 {
+        style
+            .measure_string(text, Point::zero(), Baseline::Top)
+            .next_position
+            .x
+    }
+
+    #[test]
+    fn test_with_charset() {
+        assert_eq!(
+            TextStyle::with_charset(BinaryColor::On, &ASCII),
+            TextStyle::new(BinaryColor::On)
+        );
+    }
+
+    #[test]
+    fn test_set_charset() {
+        let mut style = TextStyle::new(BinaryColor::On);
+        style.set_charset(&ASCII);
+        assert!(core::ptr::eq(style.charset(), &ASCII));
+        assert_eq!(style.line_height(), ASCII.line_height());
+    }
+
+    #[test]
+    fn test_fallback_chain() {
+        use embedded_graphics::mono_font::mapping::StrGlyphMapping;
+
+        // The ASCII charset without the lowercase letters and the ligatures,
+        // followed by the full ASCII charset.
+        static CHARSETS: [Charset<'static>; 2] = [
+            Charset {
+                glyph_mapping: StrGlyphMapping::new("\0\u{20}\u{60}", 31),
+                ligatures: crate::ligatures::Ligatures::new("", 95),
+                ..ASCII
+            },
+            ASCII,
+        ];
+        let mut style = TextStyle::new(BinaryColor::On);
+        style.set_fallback_chain(FallbackChain::new(&CHARSETS));
+        assert!(core::ptr::eq(style.charset(), &CHARSETS[0]));
+
+        // There is no kerning between the glyphs from different charsets
+        let ascii = TextStyle::new(BinaryColor::On);
+        assert_eq!(width(&ascii, "T/"), 8);
+        assert_eq!(width(&style, "T/"), 8);
+        assert_eq!(width(&ascii, "Ty"), 8);
+        assert_eq!(width(&style, "Ty"), 9);
+    }
+
+    #[test]
+    fn test_draw_string() {
+        let style = TextStyle::new(BinaryColor::On);
+        let mut display = MockDisplay::new();
+        let result = style.draw_string("Hello, World!", Point::zero(), Baseline::Top, &mut display);
+        assert_eq!(result, Ok(Point::new(45, 0)));
+        assert_eq!(
+            display,
+            MockDisplay::from_pattern(&[
+                "                                             ",
+                "#  #     # #          #  #  #         #   # #",
+                "#  #     # #          #  #  #         #   # #",
+                "#  #  ## # # ##       #  #  # ##  ##  #  ## #",
+                "#### # # # # # #      #  #  # # # # # # # # #",
+                "#  # ### # # # #      #  #  # # # # # # # # #",
+                "#  # #   # # # #      # ## #  # # #   # # #  ",
+                "#  #  ## # #  ## #     #  #    ## #   # ##  #",
+                "                 #                           ",
+                "                #                            ",
+            ])
+        );
+    }
+
+    #[test]
+    fn test_draw_string_scaled() {
+        let text = "jay!";
+        let mut style = TextStyle::new(BinaryColor::On);
+        style.set_underline_color(DecorationColor::TextColor);
+        let mut display = MockDisplay::new();
+        let result = style.draw_string(text, Point::new(2, 0), Baseline::Top, &mut display);
+
+        style.set_scale(2);
+        assert_eq!(style.scale(), 2);
+        assert_eq!(style.line_height(), 22);
+        let mut scaled_display = MockDisplay::new();
+        let scaled_result =
+            style.draw_string(text, Point::new(4, 0), Baseline::Top, &mut scaled_display);
+        assert_eq!(scaled_result, result.map(|point| point * 2));
+
+        // Every pixel is drawn as a 2×2 block
+        for point in scaled_display.affected_area().points() {
+            assert_eq!(
+                scaled_display.get_pixel(point),
+                display.get_pixel(point / 2)
+            );
+        }
+        assert_eq!(
+            scaled_display.affected_area().size,
+            display.affected_area().size * 2
+        );
+    }
+
+    #[test]
+    fn test_scale_zero_is_one() {
+        let mut style = TextStyle::new(BinaryColor::On);
+        style.set_scale(0);
+        assert_eq!(style.scale(), 1);
+    }
+
+    #[test]
+    fn test_draw_string_rotated() {
+        let text = "jay!";
+        let position = Point::new(20, 20);
+        let mut style = TextStyle::new(BinaryColor::On);
+        style.set_underline_color(DecorationColor::TextColor);
+        let mut display = MockDisplay::new();
+        style
+            .draw_string(text, position, Baseline::Alphabetic, &mut display)
+            .unwrap();
+
+        for rotation in [Rotation::Rotate90, Rotation::Rotate180, Rotation::Rotate270] {
+            style.set_rotation(rotation, position);
+            assert_eq!(style.rotation(), rotation);
+            let mut rotated_display = MockDisplay::new();
+            let result =
+                style.draw_string(text, position, Baseline::Alphabetic, &mut rotated_display);
+            let text_metrics = style.measure_string(text, position, Baseline::Alphabetic);
+            assert_eq!(result, Ok(text_metrics.next_position));
+
+            for point in rotated_display.affected_area().points() {
+                let unrotated = rotation.inverse().rotate_point(point, position);
+                assert_eq!(
+                    rotated_display.get_pixel(point),
+                    display.get_pixel(unrotated)
+                );
+            }
+            let affected_area = rotated_display.affected_area();
+            assert_eq!(
+                text_metrics.bounding_box.intersection(&affected_area),
+                affected_area
+            );
+        }
+    }
+
+    /// Asserts that the rotated display is the display rotated around the origin.
+    fn assert_rotated(
+        display: &MockDisplay<BinaryColor>,
+        rotated_display: &MockDisplay<BinaryColor>,
+        rotation: Rotation,
+        origin: Point,
+    ) {
+        assert_eq!(
+            rotated_display.affected_area(),
+            rotation.rotate_rectangle(display.affected_area(), origin)
+        );
+        for point in display.affected_area().points() {
+            assert_eq!(
+                display.get_pixel(point),
+                rotated_display.get_pixel(rotation.rotate_point(point, origin)),
+                "{:?}",
+                point
+            );
+        }
+    }
+
+    #[test]
+    fn test_draw_multiline_text_rotated() {
+        use embedded_graphics::{text::Text, Drawable};
+        let position = Point::new(20, 20);
+        let mut style = TextStyle::new(BinaryColor::On);
+        let mut display = MockDisplay::new();
+        Text::new("Hi\njoy", position, style)
+            .draw(&mut display)
+            .unwrap();
+
+        style.set_rotation(Rotation::Rotate90, position);
+        let mut rotated_display = MockDisplay::new();
+        Text::new("Hi\njoy", position, style)
+            .draw(&mut rotated_display)
+            .unwrap();
+        assert_rotated(&display, &rotated_display, Rotation::Rotate90, position);
+    }
+
+    #[test]
+    fn test_draw_text_box_rotated() {
+        use embedded_graphics::Drawable;
+        use embedded_text::TextBox;
+        let bounds = Rectangle::new(Point::new(30, 10), Size::new(30, 30));
+        let style = TextStyle::new(BinaryColor::On);
+        let mut display = MockDisplay::new();
+        TextBox::new("Hello, world!", bounds, style)
+            .draw(&mut display)
+            .unwrap();
+
+        let mut rotated_display = MockDisplay::new();
+        let target =
+            &mut RotatedDrawTarget::new(&mut rotated_display, bounds.top_left, Rotation::Rotate90);
+        TextBox::new("Hello, world!", bounds, style)
+            .draw(target)
+            .unwrap();
+        assert_rotated(
+            &display,
+            &rotated_display,
+            Rotation::Rotate90,
+            bounds.top_left,
+        );
+    }
+
+    #[test]
+    fn test_draw_string_bold() {
+        let mut style = TextStyle::new(BinaryColor::On);
+        style.set_bold(true);
+        assert!(style.is_bold());
+        let mut display = MockDisplay::new();
+        let result = style.draw_string("Hi!", Point::zero(), Baseline::Top, &mut display);
+        assert_eq!(result, Ok(Point::new(11, 0)));
+        assert_eq!(
+            display,
+            MockDisplay::from_pattern(&[
+                "           ",
+                "## ## ## ##",
+                "## ##    ##",
+                "## ## ## ##",
+                "##### ## ##",
+                "## ## ## ##",
+                "## ## ##   ",
+                "## ## ## ##",
+            ])
+        );
+        assert_eq!(
+            style.measure_string("Hi!", Point::zero(), Baseline::Top),
+            TextMetrics {
+                bounding_box: Rectangle::new(Point::zero(), Size::new(11, 11)),
+                next_position: Point::new(11, 0),
+            }
+        );
+    }
+
+    #[test]
+    fn test_draw_string_oblique() {
+        let mut style = TextStyle::new(BinaryColor::On);
+        style.set_oblique(3);
+        assert_eq!(style.oblique(), 3);
+        let mut display = MockDisplay::new();
+        let result = style.draw_string("Hey", Point::new(1, 0), Baseline::Top, &mut display);
+        assert_eq!(result, Ok(Point::new(13, 0)));
+        assert_eq!(
+            display,
+            MockDisplay::from_pattern(&[
+                "              ",
+                "   #  #       ",
+                "  #  #        ",
+                "  #  #  ## # #",
+                "  #### # # # #",
+                " #  # ### # # ",
+                " #  # #   # # ",
+                " #  #  ##  ## ",
+                "           #  ",
+                "           #  ",
+                "         ##   ",
+            ])
+        );
+        // The bounding box includes the overhangs on both sides
+        assert_eq!(
+            style.measure_string("Hey", Point::new(1, 0), Baseline::Top),
+            TextMetrics {
+                bounding_box: Rectangle::new(Point::zero(), Size::new(15, 11)),
+                next_position: Point::new(13, 0),
+            }
+        );
+    }
+
+    #[test]
+    fn test_draw_string_with_outline() {
+        let mut style = TextStyle::new(BinaryColor::On);
+        style.set_effect(TextEffect::Outline(BinaryColor::Off));
+        assert_eq!(style.effect(), TextEffect::Outline(BinaryColor::Off));
+        let mut display = MockDisplay::new();
+        display.set_allow_overdraw(true);
+        let result = style.draw_string("Hi!", Point::new(1, 0), Baseline::Top, &mut display);
+        assert_eq!(result, Ok(Point::new(9, 0)));
+        assert_eq!(
+            display,
+            MockDisplay::from_pattern(&[
+                "..........",
+                ".#..#.#.#.",
+                ".#..#...#.",
+                ".#..#.#.#.",
+                ".####.#.#.",
+                ".#..#.#.#.",
+                ".#..#.#...",
+                ".#..#.#.#.",
+                "..........",
+            ])
+        );
+        // The bounding box grows by one pixel in every direction
+        assert_eq!(
+            style.measure_string("Hi!", Point::new(1, 0), Baseline::Top),
+            TextMetrics {
+                bounding_box: Rectangle::new(Point::new(0, -1), Size::new(10, 13)),
+                next_position: Point::new(9, 0),
+            }
+        );
+    }
+
+    #[test]
+    fn test_draw_string_with_shadow() {
+        let mut style = TextStyle::new(BinaryColor::On);
+        style.set_effect(TextEffect::Shadow(BinaryColor::Off));
+        let mut display = MockDisplay::new();
+        display.set_allow_overdraw(true);
+        let result = style.draw_string("Hi!", Point::zero(), Baseline::Top, &mut display);
+        assert_eq!(result, Ok(Point::new(8, 0)));
+        assert_eq!(
+            display,
+            MockDisplay::from_pattern(&[
+                "         ",
+                "#  # # # ",
+                "#. #. .#.",
+                "#. #.# #.",
+                "####.#.#.",
+                "#..#.#.#.",
+                "#. #.#. .",
+                "#. #.#.# ",
+                " .  . . .",
+            ])
+        );
+        // The bounding box grows by one pixel to the right and to the bottom
+        assert_eq!(
+            style.measure_string("Hi!", Point::zero(), Baseline::Top),
+            TextMetrics {
+                bounding_box: Rectangle::new(Point::zero(), Size::new(9, 12)),
+                next_position: Point::new(8, 0),
+            }
+        );
     }
 
-    /// Draws the text using the binary color format.
-    fn draw_string_binary<D>(
-        &self,
-        text: &str,
-        position: Point,
-        mut target: D,
-    ) -> Result<Point, D::Error>
-    where
-        D: DrawTarget<Color = BinaryColor>,
-    {
-        let mut pos = position;
-        let mut prev_glyph = None;
+    #[test]
+    fn test_letter_and_word_spacing() {
+        let text = "Hi there";
+        let mut style = TextStyle::new(BinaryColor::On);
+        let default_width = width(&style, text);
+        let default_space_width = width(&style, " ");
 
-        for glyph in self.charset.glyph_indices(text) {
-            pos.x += self.charset.spacing(prev_glyph, glyph);
-            let area = self.charset.glyph_area(glyph);
-            self.charset
-                .image
-                .draw_sub_image(&mut target.translated(pos), &area)?;
-            pos.x += area.size.width as i32;
-            prev_glyph = Some(glyph);
-        }
+        style.set_letter_spacing(1);
+        style.set_word_spacing(2);
+        assert_eq!((style.letter_spacing(), style.word_spacing()), (1, 2));
+        assert_eq!(width(&style, text), default_width + 8 + 2);
+        // The whitespace width that embedded-text measures includes the word spacing
+        assert_eq!(width(&style, " "), default_space_width + 1 + 2);
 
-        Ok(pos)
+        style.set_word_spacing(0);
+        let mut display = MockDisplay::new();
+        let result = style.draw_string("Hi!", Point::zero(), Baseline::Top, &mut display);
+        assert_eq!(result, Ok(Point::new(11, 0)));
+        assert_eq!(
+            display,
+            MockDisplay::from_pattern(&[
+                "          ",
+                "#  #  #  #",
+                "#  #     #",
+                "#  #  #  #",
+                "####  #  #",
+                "#  #  #  #",
+                "#  #  #   ",
+                "#  #  #  #",
+            ])
+        );
     }
 
-    /// Returns the x position after the last character in the line of text.
-    fn advance_position(&self, text: &str, x: i32) -> i32 {
-        let mut x = x;
-        let mut prev_glyph = None;
-        for glyph in self.charset.glyph_indices(text) {
-            x += self.charset.spacing(prev_glyph, glyph);
-            x += self.charset.glyph_width(glyph);
-            prev_glyph = Some(glyph);
-        }
-        return x;
-    }
+    #[test]
+    fn test_disable_kerning_and_ligatures() {
+        let mut style = TextStyle::new(BinaryColor::On);
+        let kerned_width = width(&style, "fo");
+        style.set_kerning(false);
+        assert!(!style.kerning());
+        // 'f' and 'o' are kerned by -1
+        assert_eq!(width(&style, "fo"), kerned_width + 1);
 
-    /// Returns the vertical offset between the line position and the top edge of the bounding box.
-    fn baseline_offset(&self, baseline: Baseline) -> i32 {
-        match baseline {
-            Baseline::Top => 0,
-            Baseline::Bottom => (self.charset.line_height - 1) as i32,
-            Baseline::Middle => ((self.charset.line_height - 1) / 2) as i32,
-            Baseline::Alphabetic => self.charset.baseline as i32,
-        }
+        style.set_ligatures(false);
+        assert!(!style.ligatures());
+        let glyphs = |style: &TextStyle<BinaryColor>, text| style.line_glyphs(text).count();
+        assert_eq!(glyphs(&style, "ffi"), 3);
+        style.set_ligatures(true);
+        assert_eq!(glyphs(&style, "ffi"), 1);
     }
-}
 
-impl<C> TextRenderer for TextStyle<C>
-where
-    C: PixelColor,
-{
-    type Color = C;
+    #[test]
+    fn test_tabular_figures() {
+        let mut style = TextStyle::new(BinaryColor::On);
+        // The kerning between '7' and '.' moves the following digits
+        assert_ne!(width(&style, "7.5"), width(&style, "0.5"));
 
-    fn draw_string<D>(
-        &self,
-        text: &str,
-        position: Point,
-        baseline: Baseline,
-        target: &mut D,
-    ) -> Result<Point, D::Error>
-    where
-        D: DrawTarget<Color = Self::Color>,
-    {
-        let position = position - Point::new(0, self.baseline_offset(baseline));
+        style.set_figures(Figures::Tabular);
+        assert_eq!(style.figures(), Figures::Tabular);
+        assert_eq!(width(&style, "7.5"), width(&style, "0.5"));
+        assert_eq!(width(&style, "12:30"), width(&style, "88:88"));
+        assert_ne!(width(&style, "-1.5"), width(&style, "+1:5"));
 
-        // Avoid measuring the text twice if the background color is not set.
-        let mut offset = None;
+        style.set_figures(Figures::TabularWithPunctuation);
+        assert_eq!(width(&style, "-1.5"), width(&style, "+1:5"));
+        // The digits are centred in their cells
+        let offsets = |text| {
+            let mut offsets = [0; 2];
+            for (offset, line_glyph) in offsets.iter_mut().zip(style.line_glyphs(text)) {
+                *offset = line_glyph.offset;
+            }
+            offsets
+        };
+        let advance = ASCII.tabular_advance();
+        let one_width = ASCII.glyph_width(ASCII.glyph_index('1'));
+        assert_eq!(offsets("11")[1] - offsets("11")[0], advance);
+        assert_eq!(offsets("11")[0], (advance - one_width) / 2);
+    }
 
-        // We have to draw the background first, because if a glyph has a negative
-        // left side bearing or kerning, it might overlap the previous glyph.
-        if let Some(color) = self.background_color {
-            let bg_width = self.advance_position(text, 0);
-            let bg_style = PrimitiveStyle::with_fill(color);
-            Rectangle::new(
-                position,
-                Size::new(bg_width as u32, self.charset.line_height),
-            )
-            .draw_styled(&bg_style, target)?;
-            offset = Some(bg_width);
+    #[test]
+    fn test_layout() {
+        extern crate std;
+        use std::vec::Vec;
+        let mut style = TextStyle::new(BinaryColor::On);
+        let text = "Tfi!";
+        let glyphs = style.layout(text).collect::<Vec<_>>();
+        assert_eq!(glyphs.len(), 3);
+        assert_eq!(glyphs[0].bytes(), 0..1);
+        assert_eq!(glyphs[1].bytes(), 1..3);
+        assert_eq!(glyphs[1].index(), ASCII.ligatures.offset + 2);
+        assert_eq!(glyphs[2].bytes(), 3..4);
+        assert_eq!(glyphs[2].area(), ASCII.glyph_area(ASCII.glyph_index('!')));
+        assert_eq!(glyphs[2].charset(), &ASCII);
+        for (glyph, line_glyph) in glyphs.iter().zip(style.line_glyphs(text)) {
+            assert_eq!(glyph.position(), Point::new(line_glyph.offset, 0));
         }
+        let last = glyphs.last().unwrap();
+        assert_eq!(
+            last.position().x + last.advance(),
+            style
+                .measure_string(text, Point::zero(), Baseline::Top)
+                .next_position
+                .x
+        );
 
-        // Draw the text.
-        if let Some(color) = self.text_color {
-            let pos =
-                self.draw_string_binary(text, position, MogeeFontDrawTarget::new(target, color))?;
-            offset = Some(pos.x - position.x);
-        };
-
-        Ok(position
-            + Point::new(
-                offset.unwrap_or_else(|| self.advance_position(text, 0)),
-                self.baseline_offset(baseline),
-            ))
+        style.set_scale(2);
+        let scaled = style.layout(text).collect::<Vec<_>>();
+        assert_eq!(scaled[2].position(), glyphs[2].position() * 2);
+        assert_eq!(scaled[2].advance(), glyphs[2].advance() * 2);
     }
 
-    fn draw_whitespace<D>(
-        &self,
-        width: u32,
-        position: Point,
-        baseline: Baseline,
-        target: &mut D,
-    ) -> Result<Point, D::Error>
-    where
-        D: DrawTarget<Color = Self::Color>,
-    {
-        let position = position - Point::new(0, self.baseline_offset(baseline));
-        if let Some(color) = self.background_color {
-            let bg_style = PrimitiveStyle::with_fill(color);
-            Rectangle::new(position, Size::new(width, self.charset.line_height))
-                .draw_styled(&bg_style, target)?;
+    #[test]
+    fn test_caret_x_and_hit_test() {
+        let style = TextStyle::new(BinaryColor::On);
+        let text = "Hfi!";
+        let mut glyphs = style.layout(text);
+        let (h, fi) = (glyphs.next().unwrap(), glyphs.next().unwrap());
+        let h_end = h.position().x + h.advance();
+        let fi_middle = fi.position().x + fi.advance() / 2;
+        let fi_end = fi.position().x + fi.advance();
+        let end = style
+            .measure_string(text, Point::zero(), Baseline::Top)
+            .next_position
+            .x;
+
+        let carets = [(0, 0), (1, h_end), (2, fi_middle), (3, fi_end), (4, end)];
+        for (byte_offset, x) in carets {
+            assert_eq!(style.caret_x(text, byte_offset), x);
+            assert_eq!(style.hit_test(text, x), byte_offset);
         }
-        Ok(position + Point::new(width as i32, self.baseline_offset(baseline)))
+        assert_eq!(style.caret_x(text, 10), end);
+        assert_eq!(style.hit_test(text, -5), 0);
+        assert_eq!(style.hit_test(text, 100), 4);
+        assert_eq!(style.hit_test(text, h_end + 1), 1);
+        assert_eq!(style.hit_test("", 3), 0);
     }
 
-    fn measure_string(&self, text: &str, position: Point, baseline: Baseline) -> TextMetrics {
-        // The bounding box position can be to the left of the position,
-        // when the first character has a negative left side bearing,
-        // e.g. letter 'j'.
-        let bb_left = self
-            .charset
-            .glyph_indices(text)
-            .next()
-            .map(|c| self.charset.spacing(None, c))
-            .unwrap_or_default();
-
-        let bb_position = position + Point::new(bb_left, -self.baseline_offset(baseline));
-        let bb_width = self.advance_position(text, -bb_left);
-        let bb_size = Size::new(bb_width as u32, self.charset.line_height);
+    #[test]
+    fn test_truncate() {
+        let style = TextStyle::new(BinaryColor::On);
+        let text = "Very long file name";
+        let ellipsis_width = width(&style, style.ellipsis());
+        assert_eq!(
+            style.truncate(text, width(&style, text) as u32),
+            (text, false)
+        );
 
-        TextMetrics {
-            bounding_box: Rectangle::new(bb_position, bb_size),
-            next_position: position + Point::new(bb_width + bb_left, 0),
+        for max_width in ellipsis_width..width(&style, text) {
+            let (prefix, truncated) = style.truncate(text, max_width as u32);
+            assert!(truncated);
+            assert!(width(&style, prefix) + ellipsis_width <= max_width);
         }
+        let max_width = width(&style, "Very long fi") + ellipsis_width;
+        assert_eq!(
+            style.truncate(text, max_width as u32),
+            ("Very long fi", true)
+        );
+        // The trailing whitespace is removed
+        let max_width = width(&style, "Very long ") + ellipsis_width;
+        assert_eq!(style.truncate(text, max_width as u32), ("Very long", true));
     }
 
-    fn line_height(&self) -> u32 {
-        self.charset.line_height
+    #[test]
+    fn test_truncate_middle() {
+        let style = TextStyle::new(BinaryColor::On);
+        let text = "/usr/share/fonts/mogee.mogf";
+        let ellipsis_width = width(&style, style.ellipsis());
+        assert_eq!(
+            style.truncate_middle(text, width(&style, text) as u32),
+            (text, "", false)
+        );
+        for max_width in ellipsis_width..width(&style, text) {
+            let (prefix, suffix, truncated) = style.truncate_middle(text, max_width as u32);
+            assert!(truncated);
+            assert!(width(&style, prefix) + ellipsis_width + width(&style, suffix) <= max_width);
+            assert!(text.starts_with(prefix) && text.ends_with(suffix));
+        }
+        // The suffix takes up to a half of the width without the ellipsis
+        let max_width = 2 * width(&style, "mogee.mogf") + ellipsis_width + 1;
+        let (_, suffix, _) = style.truncate_middle(text, max_width as u32);
+        assert_eq!(suffix, "mogee.mogf");
     }
-}
-
-impl<C> CharacterStyle for TextStyle<C>
-where
-    C: PixelColor,
-{
-    type Color = C;
 
-    /// Sets the text color.
-    fn set_text_color(&mut self, text_color: Option<Self::Color>) {
-        self.text_color = text_color;
+    #[test]
+    fn test_draw_truncated() {
+        let style = TextStyle::new(BinaryColor::On);
+        let text = "/usr/share/fonts/mogee.mogf";
+        for truncation in [Truncation::End, Truncation::Middle] {
+            for max_width in 0..40 {
+                let mut display = MockDisplay::new();
+                let result = style.draw_truncated(
+                    text,
+                    Point::zero(),
+                    Baseline::Top,
+                    max_width,
+                    truncation,
+                    &mut display,
+                );
+                assert!(result.unwrap().x <= max_width as i32);
+                assert!(display.affected_area().size.width <= max_width);
+            }
+        }
     }
 
-    /// Sets the background color.
-    fn set_background_color(&mut self, background_color: Option<Self::Color>) {
-        self.background_color = background_color;
+    #[test]
+    fn test_draw_truncated_oblique_with_outline() {
+        let mut style = TextStyle::new(BinaryColor::On);
+        style.set_oblique(2);
+        style.set_effect(TextEffect::Outline(BinaryColor::Off));
+        let text = "/usr/share/fonts/mogee.mogf";
+        let position = Point::new(5, 1);
+        for truncation in [Truncation::End, Truncation::Middle] {
+            for max_width in 0..40 {
+                let mut display = MockDisplay::new();
+                display.set_allow_overdraw(true);
+                style
+                    .draw_truncated(
+                        text,
+                        position,
+                        Baseline::Top,
+                        max_width,
+                        truncation,
+                        &mut display,
+                    )
+                    .unwrap();
+                // The slanted glyphs and their outline stay within the maximum width.
+                let right = display.affected_area().bottom_right().map_or(0, |p| p.x);
+                assert!(right < position.x + max_width as i32);
+            }
+        }
     }
 
-    /// Underline is not supported.
-    fn set_underline_color(&mut self, _underline_color: DecorationColor<Self::Color>) {}
-
-    /// Strikethrough is not supported.
-    fn set_strikethrough_color(&mut self, _strikethrough_color: DecorationColor<Self::Color>) {}
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use embedded_graphics::{geometry::Size, mock_display::MockDisplay};
-    const TEXT_POS: Point = Point::new(4, 6);
-
     #[test]
-    fn test_draw_string() {
+    fn test_spans_are_kerned_as_one_line() {
         let style = TextStyle::new(BinaryColor::On);
-        let mut display = MockDisplay::new();
-        let result = style.draw_string("Hello, World!", Point::zero(), Baseline::Top, &mut display);
-        assert_eq!(result, Ok(Point::new(45, 0)));
+        let spans = [
+            ("f", SpanStyle::new(BinaryColor::On)),
+            ("o", SpanStyle::new(BinaryColor::On)),
+        ];
+        let metrics = style.measure_spans(&spans, TEXT_POS, Baseline::Top);
         assert_eq!(
-            display,
-            MockDisplay::from_pattern(&[
-                "                                             ",
-                "#  #     # #          #  #  #         #   # #",
-                "#  #     # #          #  #  #         #   # #",
-                "#  #  ## # # ##       #  #  # ##  ##  #  ## #",
-                "#### # # # # # #      #  #  # # # # # # # # #",
-                "#  # ### # # # #      #  #  # # # # # # # # #",
-                "#  # #   # # # #      # ## #  # # #   # # #  ",
-                "#  #  ## # #  ## #     #  #    ## #   # ##  #",
-                "                 #                           ",
-                "                #                            ",
-            ])
+            metrics.bounding_box,
+            style
+                .measure_string("fo", TEXT_POS, Baseline::Top)
+                .bounding_box
         );
+
+        let mut spans_display = MockDisplay::new();
+        let next_position = style
+            .draw_spans(&spans, TEXT_POS, Baseline::Top, &mut spans_display)
+            .unwrap();
+        assert_eq!(next_position, metrics.next_position);
+        let mut text_display = MockDisplay::new();
+        style
+            .draw_string("fo", TEXT_POS, Baseline::Top, &mut text_display)
+            .unwrap();
+        spans_display.assert_eq(&text_display);
+    }
+
+    #[test]
+    fn test_draw_spans_with_own_colors() {
+        use embedded_graphics::pixelcolor::{Rgb888, RgbColor};
+
+        let style = TextStyle::new(Rgb888::WHITE);
+        let mut error = SpanStyle::new(Rgb888::RED);
+        error.background_color = Some(Rgb888::BLUE);
+        let spans = [("Hi", SpanStyle::new(Rgb888::GREEN)), ("!", error)];
+        let mut display = MockDisplay::new();
+        display.set_allow_overdraw(true);
+        style
+            .draw_spans(&spans, Point::zero(), Baseline::Top, &mut display)
+            .unwrap();
+
+        let mut text_display = MockDisplay::new();
+        TextStyle::new(BinaryColor::On)
+            .draw_string("Hi!", Point::zero(), Baseline::Top, &mut text_display)
+            .unwrap();
+        let boundary = style.caret_x("Hi!", 2);
+        let end = style.advance_position("Hi!", 0);
+        for point in Rectangle::new(Point::zero(), Size::new(end as u32, 11)).points() {
+            let expected = match (text_display.get_pixel(point), point.x < boundary) {
+                (Some(BinaryColor::On), true) => Some(Rgb888::GREEN),
+                (Some(BinaryColor::On), false) => Some(Rgb888::RED),
+                (_, true) => None,
+                (_, false) => Some(Rgb888::BLUE),
+            };
+            assert_eq!(display.get_pixel(point), expected, "{:?}", point);
+        }
     }
 
     #[test]
@@ -264,6 +2285,83 @@ mod tests {
         }
     }
 
+    /// Asserts that the position after the drawn text is the same as the measured one.
+    ///
+    /// The text is drawn away from the display edges, so that the overhang, the effect,
+    /// the scale and the rotation all stay within the display.
+    fn assert_measured_position_same_as_drawn(style: TextStyle<BinaryColor>) {
+        let text = "jest!";
+        let text_pos = Point::new(24, 30);
+        for baseline in [
+            Baseline::Top,
+            Baseline::Bottom,
+            Baseline::Alphabetic,
+            Baseline::Middle,
+        ] {
+            let mut display = MockDisplay::new();
+            display.set_allow_overdraw(true);
+            let result = style.draw_string(text, text_pos, baseline, &mut display);
+            let text_metrics = style.measure_string(text, text_pos, baseline);
+            assert_eq!(result, Ok(text_metrics.next_position));
+        }
+    }
+
+    #[test]
+    fn measured_position_should_be_same_as_drawn_with_decorations() {
+        let mut underlined = TextStyle::new(BinaryColor::On);
+        underlined.set_underline_color(DecorationColor::Custom(BinaryColor::On));
+        assert_measured_position_same_as_drawn(underlined);
+
+        let mut struck_through = TextStyle::new(BinaryColor::On);
+        struck_through.set_strikethrough_color(DecorationColor::Custom(BinaryColor::On));
+        assert_measured_position_same_as_drawn(struck_through);
+    }
+
+    #[test]
+    fn measured_position_should_be_same_as_drawn_with_bold_and_oblique() {
+        let mut bold = TextStyle::new(BinaryColor::On);
+        bold.set_bold(true);
+        assert_measured_position_same_as_drawn(bold);
+
+        let mut oblique = TextStyle::new(BinaryColor::On);
+        oblique.set_background_color(Some(BinaryColor::Off));
+        oblique.set_oblique(2);
+        assert_measured_position_same_as_drawn(oblique);
+    }
+
+    #[test]
+    fn measured_position_should_be_same_as_drawn_with_effect() {
+        let mut outlined = TextStyle::new(BinaryColor::On);
+        outlined.set_effect(TextEffect::Outline(BinaryColor::Off));
+        assert_measured_position_same_as_drawn(outlined);
+    }
+
+    #[test]
+    fn measured_position_should_be_same_as_drawn_with_spacing() {
+        let mut spaced = TextStyle::new(BinaryColor::On);
+        spaced.set_underline_color(DecorationColor::TextColor);
+        spaced.set_letter_spacing(-1);
+        spaced.set_word_spacing(3);
+        assert_measured_position_same_as_drawn(spaced);
+    }
+
+    #[test]
+    fn measured_position_should_be_same_as_drawn_when_rotated() {
+        let mut rotated = TextStyle::new(BinaryColor::On);
+        rotated.set_background_color(Some(BinaryColor::Off));
+        rotated.set_rotation(Rotation::Rotate270, Point::new(24, 30));
+        assert_measured_position_same_as_drawn(rotated);
+    }
+
+    #[test]
+    fn measured_position_should_be_same_as_drawn_when_scaled() {
+        let mut scaled = TextStyle::new(BinaryColor::On);
+        scaled.set_underline_color(DecorationColor::TextColor);
+        scaled.set_background_color(Some(BinaryColor::Off));
+        scaled.set_scale(2);
+        assert_measured_position_same_as_drawn(scaled);
+    }
+
     #[test]
     fn test_draw_string_with_background() {
         let mut style = TextStyle::new(BinaryColor::On);
@@ -296,6 +2394,105 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_draw_string_with_underline() {
+        let mut style = TextStyle::new(BinaryColor::On);
+        style.set_underline_color(DecorationColor::TextColor);
+        let mut display = MockDisplay::new();
+        let result = style.draw_string("big joy", Point::zero(), Baseline::Top, &mut display);
+        assert_eq!(result, Ok(Point::new(22, 0)));
+
+        // The underline is interrupted around the descenders of 'g', 'j' and 'y'.
+        assert_eq!(
+            display,
+            MockDisplay::from_pattern(&[
+                "                      ",
+                "#   #        #        ",
+                "#                     ",
+                "##  #  ##    # ##  # #",
+                "# # # # #    # # # # #",
+                "# # # # #    # # # # #",
+                "# # #  #     # # # # #",
+                " ## # #      #  ##  ##",
+                "       ##    #       #",
+                "####### # ## # ##### #",
+                "      ##   ##      ## ",
+            ])
+        );
+    }
+
+    #[test]
+    fn test_draw_whitespace_with_underline() {
+        let mut style = TextStyle::new(BinaryColor::On);
+        style.set_underline_color(DecorationColor::Custom(BinaryColor::Off));
+        let mut display = MockDisplay::new();
+        let result = style.draw_whitespace(3, Point::new(0, 8), Baseline::Alphabetic, &mut display);
+        assert_eq!(result, Ok(Point::new(3, 8)));
+        display.assert_pattern(&[
+            "   ", //
+            "   ", //
+            "   ", //
+            "   ", //
+            "   ", //
+            "   ", //
+            "   ", //
+            "   ", //
+            "   ", //
+            "...", //
+        ]);
+    }
+
+    #[test]
+    fn test_draw_string_with_strikethrough() {
+        let mut style = TextStyle::new(BinaryColor::On);
+        style.set_strikethrough_color(DecorationColor::TextColor);
+        let mut display = MockDisplay::new();
+        display.set_allow_overdraw(true);
+        let result = style.draw_string("jam", Point::new(2, 0), Baseline::Top, &mut display);
+        assert_eq!(result, Ok(Point::new(13, 0)));
+
+        // The strikethrough spans the bounding box, including
+        // the negative left side bearing of letter 'j'.
+        assert_eq!(
+            display,
+            MockDisplay::from_pattern(&[
+                "             ",
+                "  #          ",
+                "             ",
+                "  # ##  #### ",
+                "  #   # # # #",
+                "#############",
+                "  # # # # # #",
+                "  #  ## # # #",
+                "  #          ",
+                "  #          ",
+                "##           ",
+            ])
+        );
+        let metrics = style.measure_string("jam", Point::new(2, 0), Baseline::Top);
+        assert_eq!(
+            metrics.bounding_box,
+            Rectangle::new(Point::zero(), Size::new(13, 11))
+        );
+    }
+
+    #[test]
+    fn test_draw_whitespace_with_strikethrough() {
+        let mut style = TextStyle::new(BinaryColor::On);
+        style.set_strikethrough_color(DecorationColor::TextColor);
+        let mut display = MockDisplay::new();
+        let result = style.draw_whitespace(2, Point::zero(), Baseline::Top, &mut display);
+        assert_eq!(result, Ok(Point::new(2, 0)));
+        display.assert_pattern(&[
+            "  ", //
+            "  ", //
+            "  ", //
+            "  ", //
+            "  ", //
+            "##", //
+        ]);
+    }
+
     #[test]
     fn test_measure_string() {
         let style = TextStyle::new(BinaryColor::On);
@@ -0,0 +1,283 @@
+use crate::text_style::{LineLayout, TextStyle};
+use core::fmt;
+use embedded_graphics::{
+    draw_target::DrawTarget,
+    geometry::Point,
+    text::{renderer::TextRenderer, Baseline},
+};
+
+/// Capacity of the buffer for the end of the line, that the following text can still change.
+const BUFFER_CAPACITY: usize = 32;
+
+/// A writer, that draws the formatted text directly to a draw target, e.g. with `write!`.
+///
+/// The text of all the `write_str` calls is laid out as one continuous line,
+/// so the kerning, ligatures, text decorations and effects match drawing the whole string
+/// with `draw_string`. The end of the text, that the following text can still change,
+/// is only drawn when the following text is written, so [`finish`](Self::finish)
+/// must be called at the end. A line break `\n` starts a new line.
+#[derive(Debug)]
+pub struct TextWriter<'a, D>
+where
+    D: DrawTarget,
+{
+    target: &'a mut D,
+    style: TextStyle<D::Color>,
+    baseline: Baseline,
+    /// Position of the start of the current line.
+    line_position: Point,
+    /// The layout before the buffered text.
+    layout: LineLayout,
+    /// Position after the drawn text.
+    position: Point,
+    /// The last drawn glyphs, followed by the text, that can still become a part of a ligature.
+    buffer: [u8; BUFFER_CAPACITY],
+    buffer_len: usize,
+    /// Length of the drawn text at the start of the buffer.
+    drawn_len: usize,
+    /// The column in font units, from which the line has to be drawn again.
+    drawn_x: i32,
+    /// The error of the draw target, that `fmt::Write` can't return.
+    error: Option<D::Error>,
+}
+
+impl<'a, D> TextWriter<'a, D>
+where
+    D: DrawTarget,
+{
+    /// Creates a new writer, that draws the text with the style
+    /// starting at the position.
+    pub fn new(
+        target: &'a mut D,
+        style: TextStyle<D::Color>,
+        position: Point,
+        baseline: Baseline,
+    ) -> Self {
+        Self {
+            target,
+            style,
+            baseline,
+            line_position: position,
+            layout: style.line_layout(),
+            position,
+            buffer: [0; BUFFER_CAPACITY],
+            buffer_len: 0,
+            drawn_len: 0,
+            drawn_x: i32::MIN,
+            error: None,
+        }
+    }
+
+    /// Returns the position after the drawn text.
+    pub fn position(&self) -> Point {
+        self.position
+    }
+
+    /// Draws the rest of the text, and returns the first error of the draw target.
+    pub fn flush(&mut self) -> Result<(), D::Error> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        self.draw_pending(true)
+    }
+
+    /// Draws the rest of the text, and returns the position after it.
+    pub fn finish(mut self) -> Result<Point, D::Error> {
+        self.flush()?;
+        Ok(self.position)
+    }
+
+    /// Draws the pending text, except for the glyphs that can still become a part
+    /// of a ligature, unless all of it is drawn.
+    ///
+    /// The last glyphs are drawn again with the next ones, but only the columns,
+    /// that the next glyphs overlap, so the underline and the effect are drawn
+    /// as if the whole line was drawn at once.
+    fn draw_pending(&mut self, all: bool) -> Result<(), D::Error> {
+        // Only the whole characters are buffered, so the text is always valid UTF-8.
+        let text = core::str::from_utf8(&self.buffer[..self.buffer_len]).unwrap_or_default();
+        let fallback_chain = self.style.fallback_chain();
+        let ligatures = self.style.ligatures();
+        let end = match all || !ligatures {
+            true => text.len(),
+            false => fallback_chain
+                .glyphs(text, ligatures)
+                .map(|(byte_offset, _)| byte_offset)
+                .filter(|&byte_offset| byte_offset >= self.drawn_len)
+                .find(|&byte_offset| fallback_chain.is_ligature_prefix(&text[byte_offset..]))
+                .unwrap_or(text.len()),
+        };
+        if end == 0 || (end == self.drawn_len && !all) {
+            return Ok(());
+        }
+        // The glyphs, that are already drawn, are drawn again from the column, that the new
+        // glyphs change, so the underline and the effect match drawing the whole line at once.
+        let next_glyph_column = self.style.next_glyph_column(&text[..end], self.layout);
+        let columns = match all {
+            true => self.drawn_x..i32::MAX,
+            false => self.drawn_x..next_glyph_column,
+        };
+        self.position = self.style.draw_columns(
+            &text[..end],
+            self.layout,
+            columns,
+            self.line_position,
+            self.baseline,
+            self.target,
+        )?;
+        self.drawn_x = self.drawn_x.max(next_glyph_column);
+        let skipped = self
+            .style
+            .skip_glyphs(&mut self.layout, &text[..end], self.drawn_x);
+        self.buffer.copy_within(skipped..self.buffer_len, 0);
+        self.buffer_len -= skipped;
+        self.drawn_len = end - skipped;
+        Ok(())
+    }
+
+    /// Starts a new line below the current one.
+    fn new_line(&mut self) {
+        let line_height = self.style.line_height() as i32;
+        self.line_position += Point::new(0, line_height);
+        self.position = self.line_position;
+        self.layout = self.style.line_layout();
+        self.buffer_len = 0;
+        self.drawn_len = 0;
+        self.drawn_x = i32::MIN;
+    }
+
+    /// Writes a character, and draws the glyphs before it, that are known.
+    fn write(&mut self, char: char) -> Result<(), D::Error> {
+        if char == '\n' {
+            self.draw_pending(true)?;
+            self.new_line();
+            return Ok(());
+        }
+        if self.buffer_len + char.len_utf8() > BUFFER_CAPACITY {
+            self.draw_pending(true)?;
+        }
+        if self.buffer_len + char.len_utf8() > BUFFER_CAPACITY {
+            // Forget the drawn glyphs, if they are too long to keep.
+            let text = core::str::from_utf8(&self.buffer[..self.buffer_len]).unwrap_or_default();
+            self.style.skip_glyphs(&mut self.layout, text, i32::MAX);
+            self.buffer_len = 0;
+            self.drawn_len = 0;
+        }
+        let end = self.buffer_len + char.len_utf8();
+        char.encode_utf8(&mut self.buffer[self.buffer_len..end]);
+        self.buffer_len = end;
+        self.draw_pending(false)
+    }
+}
+
+impl<D> fmt::Write for TextWriter<'_, D>
+where
+    D: DrawTarget,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for char in s.chars() {
+            self.write_char(char)?;
+        }
+        Ok(())
+    }
+
+    fn write_char(&mut self, char: char) -> fmt::Result {
+        if self.error.is_some() {
+            return Err(fmt::Error);
+        }
+        self.write(char).map_err(|error| {
+            self.error = Some(error);
+            fmt::Error
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TextEffect;
+    use core::fmt::Write;
+    use embedded_graphics::{
+        mock_display::MockDisplay,
+        pixelcolor::BinaryColor,
+        text::{renderer::CharacterStyle, DecorationColor},
+    };
+
+    const TEXT_POS: Point = Point::new(2, 9);
+
+    /// Asserts, that the chunks are drawn the same as the whole text, with and without
+    /// the text decorations and the effect, that depend on the neighbouring glyphs.
+    fn assert_same_as_draw_string(chunks: &[&str], text: &str) {
+        let mut underlined = TextStyle::new(BinaryColor::On);
+        underlined.set_underline_color(DecorationColor::TextColor);
+        let mut struck_through = TextStyle::new(BinaryColor::On);
+        struck_through.set_strikethrough_color(DecorationColor::Custom(BinaryColor::Off));
+        let mut outlined = TextStyle::new(BinaryColor::On);
+        outlined.set_effect(TextEffect::Outline(BinaryColor::Off));
+
+        for style in [
+            TextStyle::new(BinaryColor::On),
+            underlined,
+            struck_through,
+            outlined,
+        ] {
+            let mut display = MockDisplay::new();
+            display.set_allow_overdraw(true);
+            let mut writer = TextWriter::new(&mut display, style, TEXT_POS, Baseline::Alphabetic);
+            for chunk in chunks {
+                writer.write_str(chunk).unwrap();
+            }
+            let position = writer.finish().unwrap();
+
+            let mut expected = MockDisplay::new();
+            expected.set_allow_overdraw(true);
+            let expected_position = style
+                .draw_string(text, TEXT_POS, Baseline::Alphabetic, &mut expected)
+                .unwrap();
+            display.assert_eq(&expected);
+            assert_eq!(position, expected_position);
+        }
+    }
+
+    #[test]
+    fn test_kerning_across_chunks() {
+        assert_same_as_draw_string(&["f", "o", "7", ".5"], "fo7.5");
+    }
+
+    #[test]
+    fn test_ligatures_across_chunks() {
+        assert_same_as_draw_string(&["a", "f", "f", "ix"], "affix");
+        assert_same_as_draw_string(&["f", "f"], "ff");
+    }
+
+    #[test]
+    fn test_underline_across_chunks() {
+        // The underline skips the descenders of the next glyph, that isn't written yet.
+        assert_same_as_draw_string(&["y", "j", "g", "y", " ", "j", "o", "y"], "yjgy joy");
+        assert_same_as_draw_string(&["yjg", "y j", "oy"], "yjgy joy");
+    }
+
+    #[test]
+    fn test_format_numbers() {
+        let style = TextStyle::new(BinaryColor::On);
+        let mut display = MockDisplay::new();
+        let mut writer = TextWriter::new(&mut display, style, Point::zero(), Baseline::Top);
+        write!(writer, "{}:{:02}", 7, 5).unwrap();
+        // The digits can't start a ligature, so they are drawn right away.
+        assert_eq!(
+            writer.position(),
+            Point::new(style.advance_position("7:05", 0), 0)
+        );
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_new_line() {
+        let style = TextStyle::new(BinaryColor::On);
+        let mut display = MockDisplay::new();
+        let mut writer = TextWriter::new(&mut display, style, Point::zero(), Baseline::Top);
+        write!(writer, "Hi\nthere").unwrap();
+        let position = writer.finish().unwrap();
+        assert_eq!(position, Point::new(style.advance_position("there", 0), 11));
+    }
+}